@@ -0,0 +1,53 @@
+//! Compares how quickly `Arena` and `SoaArena` validate indices when their
+//! values are large. Run with `cargo run --release --bin validate`.
+
+use std::time::{Duration, Instant};
+
+use thunderdome::{Arena, Index, SoaArena};
+
+const ENTRIES: usize = 10_000;
+const ROUNDS: u32 = 1_000;
+
+/// Run `contains` over every index `ROUNDS` times, returning the average time
+/// taken by one pass.
+fn time_pass(indices: &[Index], contains: impl Fn(Index) -> bool) -> Duration {
+    let mut found = 0usize;
+    let start = Instant::now();
+
+    for _ in 0..ROUNDS {
+        for &index in indices {
+            if contains(index) {
+                found += 1;
+            }
+        }
+    }
+
+    let elapsed = start.elapsed();
+
+    // Using the result keeps the lookups from being optimized out.
+    assert_eq!(found, indices.len() * ROUNDS as usize);
+
+    elapsed / ROUNDS
+}
+
+fn main() {
+    let mut arena = Arena::new();
+    let mut soa = SoaArena::new();
+
+    let indices: Vec<_> = (0..ENTRIES)
+        .map(|_| {
+            soa.insert([0u8; 512]);
+            arena.insert([0u8; 512])
+        })
+        .collect();
+
+    println!("contains over {} entries of [u8; 512]", ENTRIES);
+    println!(
+        "Arena:    {:?} per pass",
+        time_pass(&indices, |index| arena.contains(index))
+    );
+    println!(
+        "SoaArena: {:?} per pass",
+        time_pass(&indices, |index| soa.contains(index))
+    );
+}
@@ -1,25 +1,100 @@
-use std::convert::TryInto;
-use std::mem::replace;
-use std::ops;
+use std::cell::RefCell;
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, HashMap};
+use std::convert::{Infallible, TryInto};
+use std::mem::{replace, size_of, swap, take};
+use std::num::NonZeroU64;
+use std::ops::{self, Bound, RangeBounds};
+use std::sync::atomic::{AtomicUsize, Ordering as AtomicOrdering};
 
+use crate::batch::BatchOps;
 use crate::drain::Drain;
+use crate::error::{
+    CorruptionError, Get2Error, ImportStateError, InvalidateError, MergeError, VersionError,
+};
+use crate::forensics::SlotForensics;
 use crate::free_pointer::FreePointer;
+use crate::frozen::{FrozenArena, FrozenSlot};
 use crate::generation::Generation;
+use crate::hooks::GenerationHooks;
 use crate::into_iter::IntoIter;
 use crate::iter::Iter;
 use crate::iter_mut::IterMut;
+use crate::mark_set::MarkSet;
+use crate::raw::{RawArena, RawSlot};
+use crate::rest::ArenaRest;
+use crate::side_table::SideTable;
+use crate::state::ArenaState;
 
 /// Container that can have elements inserted into it and removed from it.
 ///
 /// Indices use the [`Index`] type, created by inserting values with [`Arena::insert`].
+///
+/// Cloning an arena clones its values and bookkeeping, but not the callbacks
+/// installed with [`Arena::set_generation_warning`] or
+/// [`Arena::on_generation_exhausted`], since callbacks can't be cloned. The
+/// clone starts out without any.
 #[derive(Debug, Clone)]
 pub struct Arena<T> {
     storage: Vec<Entry<T>>,
     len: u32,
+    peak_len: u32,
+    placeholders: u32,
     first_free: Option<FreePointer>,
+    recycled: Vec<T>,
+    hooks: GenerationHooks,
+    next_entity_id: Option<NonZeroU64>,
+    auto_trim: bool,
+    provenance: Option<u8>,
+    epoch: u64,
+    tags: SideTable<u16>,
+    versions: SideTable<u64>,
+    entity_ids: SideTable<Option<NonZeroU64>>,
+    padding: SideTable<bool>,
+}
+
+/// Decision returned from the callback given to [`Arena::iter_mut_cull`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cull {
+    /// Keep the entry in the arena.
+    Keep,
+
+    /// Remove the entry from the arena, dropping its value.
+    Remove,
+}
+
+/// Token recording where a value lived when it was resolved with
+/// [`Arena::resolve`], to be checked again later by
+/// [`Arena::apply_resolved_mut`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ResolvedRef {
+    slot: u32,
+    generation: Generation,
+}
+
+impl ResolvedRef {
+    /// Returns the index that this token was resolved from.
+    pub fn index(self) -> Index {
+        Index {
+            slot: self.slot,
+            generation: self.generation,
+        }
+    }
 }
 
+/// Mask for the generation bits that are kept by `Index::to_bits_with_tag`.
+const TAGGED_GENERATION_MASK: u32 = 0x00FF_FFFF;
+
+/// Number of arenas created with [`Arena::with_provenance_checks`] so far,
+/// which picks the id of the next one. See [`provenance_id`].
+static PROVENANCE_ARENAS: AtomicUsize = AtomicUsize::new(0);
+
 /// Index type for [`Arena`] that has a generation attached to it.
+///
+/// A slot's generation wraps around after 2^32 reuses, or after 2^24 reuses in
+/// an arena created with [`Arena::with_provenance_checks`], whose ids take the
+/// top 8 bits of every generation. An index kept across that many reuses of
+/// its slot can become valid again.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 pub struct Index {
     pub(crate) slot: u32,
@@ -29,7 +104,7 @@ pub struct Index {
 impl Index {
     /// Convert this `Index` to an equivalent `u64` representation. Mostly
     /// useful for passing to code outside of Rust.
-    #[allow(clippy::integer_arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
     pub fn to_bits(self) -> u64 {
         // This is safe because a `u32` bit-shifted by 32 will still fit in a `u64`.
         ((self.generation.to_u32() as u64) << 32) | (self.slot as u64)
@@ -41,7 +116,7 @@ impl Index {
     ///
     /// If fed an index which was not generated by thunderdome or even just run
     /// `Index::from_bits(0)`, this function may panic!
-    #[allow(clippy::integer_arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
     pub fn from_bits(bits: u64) -> Self {
         // By bit-shifting right by 32, we're undoing the left-shift in `to_bits`
         // thus this is okay by the same rationale.
@@ -51,16 +126,102 @@ impl Index {
         Self { generation, slot }
     }
 
+    /// Convert back from a value generated with `Index::to_bits`, returning
+    /// `None` instead of panicking if the bits can't describe an index.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn try_from_bits(bits: u64) -> Option<Self> {
+        let generation = Generation::try_from_u32((bits >> 32) as u32)?;
+        let slot = bits as u32;
+
+        Some(Self { generation, slot })
+    }
+
+    /// Returns true if `bits` could have been produced by [`Index::to_bits`],
+    /// without consulting any arena. Useful as a cheap sanity check on
+    /// indices received across an FFI boundary, before checking whether an
+    /// arena contains them.
+    ///
+    /// This accepts exactly the bits that [`Index::try_from_bits`] accepts.
+    pub fn is_well_formed_bits(bits: u64) -> bool {
+        Self::try_from_bits(bits).is_some()
+    }
+
+    /// Convert this `Index` to a `u64` representation with an 8-bit tag packed
+    /// into the highest bits. Useful for tagged handle systems that need to
+    /// know which kind of object an index refers to.
+    ///
+    /// The tag takes the place of the top 8 bits of the generation, so only
+    /// generations below 2²⁴ can be represented. This function will panic if
+    /// the index's generation is outside of that range, which is always the
+    /// case for indices from an arena created with
+    /// [`Arena::with_provenance_checks`]. See [`Index::try_to_bits_with_tag`].
+    pub fn to_bits_with_tag(self, tag: u8) -> u64 {
+        match self.try_to_bits_with_tag(tag) {
+            Some(bits) => bits,
+            None => panic!(
+                "generation {} is too large to be packed with a tag",
+                self.generation.to_u32()
+            ),
+        }
+    }
+
+    /// Like [`Index::to_bits_with_tag`], but returns `None` instead of
+    /// panicking if the index's generation is 2²⁴ or above.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn try_to_bits_with_tag(self, tag: u8) -> Option<u64> {
+        let generation = self.generation.to_u32();
+        if generation > TAGGED_GENERATION_MASK {
+            return None;
+        }
+
+        // These shifts can't lose information: the tag fits in the top 8 bits
+        // and the generation was just checked to fit in the 24 below it.
+        Some(((tag as u64) << 56) | ((generation as u64) << 32) | (self.slot as u64))
+    }
+
+    /// Convert back from a value generated with `Index::to_bits_with_tag`,
+    /// returning the tag alongside the index.
+    ///
+    /// Like `Index::from_bits`, this function may panic if given bits that
+    /// were not produced by thunderdome.
+    #[allow(clippy::arithmetic_side_effects)]
+    pub fn from_bits_with_tag(bits: u64) -> (u8, Self) {
+        // Undoing the shifts in `to_bits_with_tag`.
+        let tag = (bits >> 56) as u8;
+        let generation = Generation::from_u32((bits >> 32) as u32 & TAGGED_GENERATION_MASK);
+        let slot = bits as u32;
+
+        (tag, Self { generation, slot })
+    }
+
     /// Convert this `Index` into a slot, discarding its generation. Slots describe a
     /// location in an [`Arena`] and are reused when entries are removed.
     pub fn slot(self) -> u32 {
         self.slot
     }
+
+    /// Mix the bits of this `Index` into a well-distributed `u64`, for use
+    /// as a bucket index in hand-written hash tables. Both the low and high
+    /// bits of the result are spread out, even for sequential indices.
+    ///
+    /// This uses Fibonacci hashing, which is fast but not cryptographic, and
+    /// offers no protection against deliberately colliding keys.
+    pub fn mixed_hash(self) -> u64 {
+        // 2^64 divided by the golden ratio, rounded to odd.
+        const FIBONACCI: u64 = 0x9E37_79B9_7F4A_7C15;
+
+        let hash = self.to_bits().wrapping_mul(FIBONACCI);
+
+        // The multiplication only carries entropy upward, so fold the high
+        // bits back down to spread out the low bits too.
+        hash ^ hash.rotate_right(32)
+    }
 }
 
 #[derive(Debug, Clone)]
 pub(crate) enum Entry<T> {
     Occupied(OccupiedEntry<T>),
+    Placeholder(Generation),
     Empty(EmptyEntry),
 }
 
@@ -69,6 +230,15 @@ impl<T> Entry<T> {
     fn into_value(self) -> Option<T> {
         match self {
             Entry::Occupied(occupied) => Some(occupied.value),
+            Entry::Placeholder(_) | Entry::Empty(_) => None,
+        }
+    }
+
+    /// If the entry is occupied or a placeholder, return its generation.
+    fn live_generation(&self) -> Option<Generation> {
+        match self {
+            Entry::Occupied(occupied) => Some(occupied.generation),
+            Entry::Placeholder(generation) => Some(*generation),
             Entry::Empty(_) => None,
         }
     }
@@ -77,7 +247,7 @@ impl<T> Entry<T> {
     fn get_empty(&self) -> Option<EmptyEntry> {
         match self {
             Entry::Empty(empty) => Some(*empty),
-            Entry::Occupied(_) => None,
+            Entry::Occupied(_) | Entry::Placeholder(_) => None,
         }
     }
 }
@@ -100,7 +270,19 @@ impl<T> Arena<T> {
         Self {
             storage: Vec::new(),
             len: 0,
+            peak_len: 0,
+            placeholders: 0,
             first_free: None,
+            recycled: Vec::new(),
+            hooks: GenerationHooks::default(),
+            next_entity_id: None,
+            auto_trim: false,
+            provenance: None,
+            epoch: 0,
+            tags: SideTable::default(),
+            versions: SideTable::default(),
+            entity_ids: SideTable::default(),
+            padding: SideTable::default(),
         }
     }
 
@@ -110,35 +292,736 @@ impl<T> Arena<T> {
         Self {
             storage: Vec::with_capacity(capacity),
             len: 0,
+            peak_len: 0,
+            placeholders: 0,
+            first_free: None,
+            recycled: Vec::new(),
+            hooks: GenerationHooks::default(),
+            next_entity_id: None,
+            auto_trim: false,
+            provenance: None,
+            epoch: 0,
+            tags: SideTable::default(),
+            versions: SideTable::default(),
+            entity_ids: SideTable::default(),
+            padding: SideTable::default(),
+        }
+    }
+
+    /// Construct an empty arena that, in debug builds, checks that indices
+    /// given to [`Arena::get`] and [`Arena::get_mut`] were issued by this
+    /// arena, panicking if they weren't.
+    ///
+    /// The arena stores a small id in the top 8 bits of each generation it
+    /// hands out, so the check costs nothing extra in memory. In exchange,
+    /// generations wrap around after 2^24 reuses of a slot instead of 2^32,
+    /// and the arena's indices can't be packed with
+    /// [`Index::to_bits_with_tag`], since the id occupies the tag's bits.
+    ///
+    /// Ids cycle through a pool of 255, so every 255th arena created this way
+    /// shares an id, and arenas sharing an id won't catch misuse between each
+    /// other.
+    pub fn with_provenance_checks() -> Self {
+        let id = provenance_id(PROVENANCE_ARENAS.fetch_add(1, AtomicOrdering::Relaxed));
+
+        Self {
+            provenance: Some(id),
+            ..Self::new()
+        }
+    }
+
+    /// Construct an empty arena with storage capacity for `slots` slots.
+    ///
+    /// This only reserves memory, exactly like [`Arena::with_capacity`]. No
+    /// slots are created, so the free list starts out empty and the first
+    /// insertion still goes into slot 0 with the first generation.
+    pub fn with_slot_capacity(slots: u32) -> Self {
+        Self::with_capacity(slots as usize)
+    }
+
+    /// Construct an arena with the same shape as `other`, so that every index
+    /// contained in `other` is also contained in the new arena. The value for
+    /// each index is produced by `fill`.
+    ///
+    /// Empty slots, placeholders, and the order of the free list are mirrored
+    /// too, so as long as both arenas see the same sequence of insertions and
+    /// removals afterwards, they will keep handing out the same indices.
+    pub fn with_shape_of<U, F: FnMut(Index) -> T>(other: &Arena<U>, mut fill: F) -> Self {
+        match other.try_map(|index, _| Ok::<T, Infallible>(fill(index))) {
+            Ok(arena) => arena,
+            Err(never) => match never {},
+        }
+    }
+
+    /// Construct a new arena by applying `f` to every value in this arena,
+    /// stopping at the first error. On success, every index contained in this
+    /// arena is also contained in the new arena, and empty slots and the free
+    /// list are mirrored like with [`Arena::with_shape_of`]. Provenance checks
+    /// carry over too, so the new arena accepts this arena's indices.
+    pub fn try_map<U, E, F: FnMut(Index, &T) -> Result<U, E>>(
+        &self,
+        mut f: F,
+    ) -> Result<Arena<U>, E> {
+        let mut storage = Vec::with_capacity(self.storage.capacity());
+
+        for (slot, entry) in (0u32..).zip(self.storage.iter()) {
+            storage.push(match entry {
+                Entry::Occupied(occupied) => {
+                    let index = Index {
+                        slot,
+                        generation: occupied.generation,
+                    };
+
+                    Entry::Occupied(OccupiedEntry {
+                        generation: occupied.generation,
+                        value: f(index, &occupied.value)?,
+                    })
+                }
+                Entry::Placeholder(generation) => Entry::Placeholder(*generation),
+                Entry::Empty(empty) => Entry::Empty(*empty),
+            });
+        }
+
+        Ok(Arena {
+            storage,
+            len: self.len,
+            peak_len: self.len,
+            placeholders: self.placeholders,
+            first_free: self.first_free,
+            recycled: Vec::new(),
+            hooks: GenerationHooks::default(),
+            next_entity_id: None,
+            auto_trim: false,
+            provenance: self.provenance,
+            epoch: 0,
+            tags: self.tags.clone(),
+            versions: SideTable::default(),
+            entity_ids: SideTable::default(),
+            padding: self.padding.clone(),
+        })
+    }
+
+    /// Clone the arena, giving the clone's storage room for at least
+    /// `capacity` slots. Every index valid in this arena is valid in the
+    /// clone, and the free list is cloned as-is.
+    ///
+    /// Useful for snapshots that will keep growing after they're taken.
+    pub fn clone_with_capacity(&self, capacity: usize) -> Self
+    where
+        T: Clone,
+    {
+        let mut storage = Vec::with_capacity(capacity.max(self.storage.len()));
+        storage.extend(self.storage.iter().cloned());
+
+        Self {
+            storage,
+            len: self.len,
+            peak_len: self.peak_len,
+            placeholders: self.placeholders,
+            first_free: self.first_free,
+            recycled: self.recycled.clone(),
+            hooks: self.hooks.clone(),
+            next_entity_id: self.next_entity_id,
+            auto_trim: self.auto_trim,
+            provenance: self.provenance,
+            epoch: self.epoch,
+            tags: self.tags.clone(),
+            versions: self.versions.clone(),
+            entity_ids: self.entity_ids.clone(),
+            padding: self.padding.clone(),
+        }
+    }
+
+    /// Construct an arena around existing storage, linking every empty entry
+    /// into the free list in ascending order.
+    pub(crate) fn from_storage(storage: Vec<Entry<T>>) -> Self {
+        Self::new().with_storage(storage)
+    }
+
+    /// Replace this arena's storage, keeping its settings, and link every
+    /// empty entry into the free list in ascending order.
+    pub(crate) fn with_storage(self, storage: Vec<Entry<T>>) -> Self {
+        let len = storage
+            .iter()
+            .filter(|entry| matches!(entry, Entry::Occupied(_)))
+            .count()
+            .try_into()
+            .unwrap_or_else(|_| panic!("Cannot insert more than u32::MAX elements into Arena"));
+        let placeholders = count_placeholders(&storage);
+
+        let mut arena = Self {
+            storage,
+            len,
+            peak_len: self.peak_len.max(len),
+            placeholders,
             first_free: None,
+            ..self
+        };
+        arena.rebuild_free_list_ascending();
+        arena
+    }
+
+    /// Combine this arena with `other`, keeping every value at its original
+    /// slot and generation so that indices from both arenas stay valid.
+    ///
+    /// Fails with [`MergeError::SlotCollision`] if any slot holds a value or
+    /// placeholder in both arenas, which [`Arena::slots_overlap`] can check
+    /// ahead of time. Settings like auto trimming are taken from this arena,
+    /// and values from `other` are given new entity ids by this arena, as if
+    /// they had just been inserted. Empty slots keep the newer of their two
+    /// generations, and the merged free list reuses empty slots in ascending
+    /// order.
+    pub fn merge_preserving(mut self, mut other: Arena<T>) -> Result<Self, MergeError> {
+        if let Some(slot) = self.first_overlapping_slot(&other) {
+            return Err(MergeError::SlotCollision { slot });
+        }
+
+        let mut storage = take(&mut self.storage);
+
+        for (slot, theirs) in (0u32..).zip(take(&mut other.storage)) {
+            if let Entry::Occupied(_) = theirs {
+                self.copy_slot_data(&other, slot);
+            }
+
+            let ours = match storage.get_mut(slot as usize) {
+                Some(ours) => ours,
+                None => {
+                    storage.push(theirs);
+                    continue;
+                }
+            };
+
+            match (ours, theirs) {
+                (Entry::Empty(ours), Entry::Empty(theirs)) => {
+                    ours.generation = ours.generation.max(theirs.generation);
+                }
+                (_, Entry::Empty(_)) => {}
+                (ours, theirs) => *ours = theirs,
+            }
+        }
+
+        Ok(self.with_storage(storage))
+    }
+
+    /// Convert the arena into a compact, read-only [`FrozenArena`] that keeps
+    /// every index valid. Turn it back into an arena with
+    /// [`FrozenArena::thaw`].
+    pub fn freeze(mut self) -> FrozenArena<T> {
+        let storage = take(&mut self.storage);
+        let mut slots = Vec::with_capacity(storage.len());
+        let mut values = Vec::with_capacity(self.len as usize);
+
+        for entry in storage {
+            slots.push(match entry {
+                Entry::Occupied(occupied) => {
+                    let position: u32 = values.len().try_into().unwrap_or_else(|_| {
+                        unreachable!("Arena storage exceeded what can be represented by a u32")
+                    });
+
+                    values.push(occupied.value);
+                    FrozenSlot::Occupied {
+                        generation: occupied.generation,
+                        position,
+                    }
+                }
+                Entry::Placeholder(generation) => FrozenSlot::Placeholder(generation),
+                Entry::Empty(empty) => FrozenSlot::Empty(empty.generation),
+            });
+        }
+
+        self.len = 0;
+        self.first_free = None;
+
+        FrozenArena {
+            slots: slots.into_boxed_slice(),
+            values: values.into_boxed_slice(),
+            shell: self,
         }
     }
 
+    /// Install a callback that is invoked when an entry whose generation is at
+    /// least `threshold` is removed or invalidated, giving advance warning
+    /// before a slot's generation wraps around.
+    ///
+    /// The callback receives the slot and the generation the entry had before
+    /// it was removed or invalidated, and is invoked at most once per slot.
+    /// Installing a new callback replaces the previous one. Clones of the
+    /// arena don't get the callback.
+    pub fn set_generation_warning(
+        &mut self,
+        threshold: u32,
+        callback: Box<dyn FnMut(u32, u32) + Send + Sync>,
+    ) {
+        self.hooks.set_warning(threshold, callback);
+    }
+
+    /// Install a callback that is invoked with a slot's number every time an
+    /// entry in that slot is removed or invalidated while its generation is
+    /// close to wrapping around. Slots reported this way are good candidates
+    /// for retirement, since reusing them risks resurrecting stale indices.
+    ///
+    /// Installing a new callback replaces the previous one. The threshold can
+    /// be configured with [`Arena::set_generation_exhaustion_threshold`].
+    /// Clones of the arena don't get the callback, but do keep the threshold.
+    pub fn on_generation_exhausted<F>(&mut self, callback: F)
+    where
+        F: FnMut(u32) + Send + Sync + 'static,
+    {
+        self.hooks.set_exhausted(Box::new(callback));
+    }
+
+    /// Set the generation at which [`Arena::on_generation_exhausted`]
+    /// considers a slot close to exhausted. Defaults to `0xFFFF_0000`.
+    pub fn set_generation_exhaustion_threshold(&mut self, threshold: u32) {
+        self.hooks.set_exhaustion_threshold(threshold);
+    }
+
+    /// Set whether removing the value in the last slot of the arena's storage
+    /// with [`Arena::remove`] or [`Arena::remove_by_slot`] should also release
+    /// any empty slots at the end of storage, like
+    /// [`Arena::shrink_to_fit_safe`] does without shrinking capacity.
+    ///
+    /// This keeps storage tight when values are removed in stack order. As
+    /// with `shrink_to_fit_safe`, stale indices pointing at released slots may
+    /// become valid again once the arena grows back over those slots.
+    pub fn set_auto_trim(&mut self, enabled: bool) {
+        self.auto_trim = enabled;
+    }
+
     /// Return the number of elements contained in the arena.
     pub fn len(&self) -> usize {
         self.len as usize
     }
 
+    /// Return the number of elements contained in the arena as a `u64`, which
+    /// can be summed across many arenas without overflowing on 32-bit
+    /// targets.
+    pub fn len_u64(&self) -> u64 {
+        u64::from(self.len)
+    }
+
+    /// Return the number of slots in the arena's storage as a `u64`, counting
+    /// occupied, reserved, and empty slots.
+    pub fn total_slots_u64(&self) -> u64 {
+        self.storage.len() as u64
+    }
+
+    /// Return the largest number of elements the arena has contained at once
+    /// over its lifetime. Useful for sizing a replacement arena with
+    /// [`Arena::with_capacity`].
+    pub fn peak_len(&self) -> usize {
+        self.peak_len as usize
+    }
+
+    /// Return the arena's modification epoch, which advances every time a
+    /// value is inserted, removed, moved, or has its index invalidated.
+    /// Reading from the arena or mutating values in place leaves it unchanged.
+    ///
+    /// Readers can remember the epoch alongside an index, then compare it
+    /// against the current epoch later to cheaply tell whether the arena may
+    /// have changed shape since. See [`Arena::get_with_epoch`].
+    pub fn epoch(&self) -> u64 {
+        self.epoch
+    }
+
     /// Return the number of elements the arena can hold without allocating,
     /// including the elements currently in the arena.
     pub fn capacity(&self) -> usize {
         self.storage.capacity()
     }
 
+    /// Return the number of bytes of heap memory used to store the arena's
+    /// entries and their bookkeeping, including spare capacity.
+    pub fn memory_footprint(&self) -> usize {
+        self.storage
+            .capacity()
+            .checked_mul(size_of::<Entry<T>>())
+            .and_then(|storage| storage.checked_add(self.side_table_footprint()))
+            .unwrap_or_else(|| unreachable!())
+    }
+
+    /// Return the number of bytes of heap memory used by the side tables.
+    pub(crate) fn side_table_footprint(&self) -> usize {
+        self.tags
+            .memory_footprint()
+            .checked_add(self.versions.memory_footprint())
+            .and_then(|total| total.checked_add(self.entity_ids.memory_footprint()))
+            .and_then(|total| total.checked_add(self.padding.memory_footprint()))
+            .unwrap_or_else(|| unreachable!())
+    }
+
+    /// Reserve capacity for at least `additional` more slots at the end of the
+    /// arena's storage, not counting any empty slots that are waiting to be
+    /// reused.
+    pub fn reserve(&mut self, additional: usize) {
+        self.storage.reserve(additional);
+    }
+
     /// Returns whether the arena is empty.
     pub fn is_empty(&self) -> bool {
         self.len == 0
     }
 
+    /// Returns the arena's storage, one entry per slot.
+    pub(crate) fn entries(&self) -> &[Entry<T>] {
+        &self.storage
+    }
+
+    /// Returns the arena's storage mutably, one entry per slot.
+    pub(crate) fn entries_mut(&mut self) -> &mut [Entry<T>] {
+        &mut self.storage
+    }
+
     /// Insert a new value into the arena, returning an index that can be used
     /// to later retrieve the value.
+    ///
+    /// The value is given a tag of `0`. See [`Arena::insert_tagged`].
     pub fn insert(&mut self, value: T) -> Index {
-        // This value will definitely be inserted, so we can update length now.
-        self.len = self
+        self.insert_tagged(value, 0)
+    }
+
+    /// Insert a new value into the arena with the given tag, returning an
+    /// index that can be used to later retrieve the value.
+    ///
+    /// Tags can be used to logically partition a single arena, and can be
+    /// iterated over with [`Arena::iter_tagged`].
+    pub fn insert_tagged(&mut self, value: T, tag: u16) -> Index {
+        let len = self
+            .len
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+
+        let index =
+            self.insert_entry(|generation| Entry::Occupied(OccupiedEntry { generation, value }));
+        self.init_slot_data(index.slot, tag);
+
+        // Only count the value once it's actually in storage, so that a panic
+        // while growing storage can't leave the length overcounting.
+        self.len = len;
+        self.peak_len = self.peak_len.max(self.len);
+
+        index
+    }
+
+    /// Attempt to insert a new value into the arena, handing the value back
+    /// instead of panicking if the arena already holds `u32::MAX` elements.
+    pub fn try_insert(&mut self, value: T) -> Result<Index, T> {
+        if self.len == u32::MAX {
+            return Err(value);
+        }
+
+        Ok(self.insert(value))
+    }
+
+    /// Insert a new value into the arena, returning its index along with the
+    /// number of additional values that can be inserted before the arena's
+    /// storage needs to reallocate.
+    ///
+    /// The headroom counts both empty slots waiting to be reused and unused
+    /// capacity at the end of storage. It's computed from counters the arena
+    /// already keeps, so this is as cheap as a plain insertion.
+    pub fn insert_with_headroom(&mut self, value: T) -> (Index, usize) {
+        let index = self.insert(value);
+
+        // Every slot that isn't occupied or reserved by a placeholder is either
+        // empty or not created yet.
+        let headroom = self
+            .storage
+            .capacity()
+            .checked_sub(self.len as usize)
+            .and_then(|headroom| headroom.checked_sub(self.placeholders as usize))
+            .unwrap_or_else(|| unreachable!());
+
+        (index, headroom)
+    }
+
+    /// Append `count` new values to the end of the arena's storage, in
+    /// consecutive slots, calling `f` with each slot to produce its value.
+    /// Returns the first slot of the block along with the new indices.
+    ///
+    /// Empty slots in the free list are not used, so the block is always
+    /// contiguous.
+    pub fn fill_block<F: FnMut(u32) -> T>(&mut self, count: usize, mut f: F) -> (u32, Vec<Index>) {
+        let start: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        self.storage.reserve(count);
+        let mut indices = Vec::with_capacity(count);
+
+        for slot in (start..).take(count) {
+            let len = self
+                .len
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+            let generation = first_generation(self.provenance);
+
+            self.storage.push(Entry::Occupied(OccupiedEntry {
+                generation,
+                value: f(slot),
+            }));
+            self.init_slot_data(slot, 0);
+
+            self.len = len;
+            self.epoch = self.epoch.wrapping_add(1);
+            indices.push(Index { slot, generation });
+        }
+
+        self.peak_len = self.peak_len.max(self.len);
+
+        (start, indices)
+    }
+
+    /// Insert a new value into the arena, placing it in `preferred_slot` if
+    /// that slot is currently empty. Otherwise, the value is inserted as if by
+    /// [`Arena::insert`].
+    ///
+    /// Useful for keeping related values close together, like reusing the slot
+    /// of a value that was just removed. If the preferred slot isn't at the
+    /// head of the free list, finding it requires walking the free list.
+    pub fn insert_reusing(&mut self, preferred_slot: u32, value: T) -> Index {
+        match self.insert_into_empty(preferred_slot, value) {
+            Ok(index) => index,
+            Err(value) => self.insert(value),
+        }
+    }
+
+    /// Insert a new value into the arena at the given slot if that slot is
+    /// empty, growing the arena if the slot is past the end of its storage.
+    ///
+    /// If the slot is already occupied, or reserved by a placeholder, the value
+    /// is handed back untouched. Any slots added to reach `slot` are left
+    /// empty and will be reused by future insertions.
+    pub fn insert_if_slot_free(&mut self, slot: u32, value: T) -> Result<Index, T> {
+        if let Some(&Entry::Empty(_)) = self.storage.get(slot as usize) {
+            return self.insert_into_empty(slot, value);
+        }
+
+        let start: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
+            unreachable!("Arena storage exceeded what can be represented by a u32")
+        });
+
+        if slot < start {
+            return Err(value);
+        }
+
+        let len = self
+            .len
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
+
+        for padding in start..slot {
+            self.storage.push(Entry::Empty(EmptyEntry {
+                generation: first_generation(self.provenance),
+                next_free: self.first_free,
+            }));
+            self.first_free = Some(FreePointer::from_slot(padding));
+            self.padding.set(padding, true);
+        }
+
+        let generation = first_generation(self.provenance);
+        self.storage
+            .push(Entry::Occupied(OccupiedEntry { generation, value }));
+        self.init_slot_data(slot, 0);
+
+        self.len = len;
+        self.peak_len = self.peak_len.max(self.len);
+        self.epoch = self.epoch.wrapping_add(1);
+
+        Ok(Index { slot, generation })
+    }
+
+    /// Insert a new value into the given slot if it's an empty entry within
+    /// storage, unlinking it from the free list wherever it sits. Otherwise,
+    /// the value is handed back.
+    fn insert_into_empty(&mut self, slot: u32, value: T) -> Result<Index, T> {
+        let next_free = match self.storage.get(slot as usize).and_then(Entry::get_empty) {
+            Some(empty) => empty.next_free,
+            None => return Err(value),
+        };
+
+        let len = self
             .len
             .checked_add(1)
             .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into Arena"));
 
+        if self.first_free.map(FreePointer::slot) == Some(slot) {
+            self.first_free = next_free;
+        } else {
+            let mut current = self.first_free;
+
+            while let Some(free_pointer) = current {
+                let empty = match &mut self.storage[free_pointer.slot() as usize] {
+                    Entry::Empty(empty) => empty,
+                    _ => unreachable!("free list pointed to a non-empty entry"),
+                };
+
+                if empty.next_free.map(FreePointer::slot) == Some(slot) {
+                    empty.next_free = next_free;
+                    break;
+                }
+
+                current = empty.next_free;
+            }
+        }
+
+        let entry = &mut self.storage[slot as usize];
+        let empty = entry.get_empty().unwrap_or_else(|| unreachable!());
+        let generation = next_generation(self.provenance, empty.generation);
+
+        *entry = Entry::Occupied(OccupiedEntry { generation, value });
+        self.init_slot_data(slot, 0);
+
+        self.len = len;
+        self.peak_len = self.peak_len.max(self.len);
+        self.epoch = self.epoch.wrapping_add(1);
+
+        Ok(Index { slot, generation })
+    }
+
+    /// Insert a value into the arena, reusing a value previously removed with
+    /// [`Arena::remove_recycling`] if there is one, or constructing a new one
+    /// with `T::default()` otherwise. The value is passed to `init` before
+    /// being inserted.
+    ///
+    /// Useful for values that own allocations, like buffers, that are
+    /// expensive to create from scratch.
+    pub fn insert_recycled<F: FnOnce(&mut T)>(&mut self, init: F) -> Index
+    where
+        T: Default,
+    {
+        let mut value = self.recycled.pop().unwrap_or_default();
+        init(&mut value);
+        self.insert(value)
+    }
+
+    /// Insert a new value into the arena, then call `after` with its index and
+    /// the whole arena, returning the new index.
+    ///
+    /// Useful for linking the new value to other values in the arena, like
+    /// adding a new node to its parent's list of children.
+    pub fn insert_and_then<F: FnOnce(Index, &mut Self)>(&mut self, value: T, after: F) -> Index {
+        let index = self.insert(value);
+        after(index, self);
+        index
+    }
+
+    /// Reserve a slot in the arena without a value, returning an index that
+    /// can later be given a value with [`Arena::fulfill`].
+    ///
+    /// Until it's fulfilled, the placeholder doesn't count toward the arena's
+    /// length, isn't visited during iteration, and `get` with its index will
+    /// return `None`.
+    pub fn reserve_placeholder(&mut self) -> Index {
+        let index = self.insert_entry(Entry::Placeholder);
+        self.placeholders = self
+            .placeholders
+            .checked_add(1)
+            .unwrap_or_else(|| unreachable!("more placeholders than slots"));
+        index
+    }
+
+    /// Give a value to a placeholder created by [`Arena::reserve_placeholder`].
+    ///
+    /// If the index doesn't refer to a placeholder in this arena, the value is
+    /// handed back as an error.
+    pub fn fulfill(&mut self, index: Index, value: T) -> Result<(), T> {
+        self.check_provenance(index);
+
+        let entry = match self.storage.get_mut(index.slot as usize) {
+            Some(entry) => entry,
+            None => return Err(value),
+        };
+
+        match entry {
+            Entry::Placeholder(generation) if *generation == index.generation => {
+                let len = self.len.checked_add(1).unwrap_or_else(|| {
+                    panic!("Cannot insert more than u32::MAX elements into Arena")
+                });
+
+                *entry = Entry::Occupied(OccupiedEntry {
+                    generation: index.generation,
+                    value,
+                });
+                self.init_slot_data(index.slot, 0);
+
+                self.len = len;
+                self.placeholders = self
+                    .placeholders
+                    .checked_sub(1)
+                    .unwrap_or_else(|| unreachable!());
+                self.peak_len = self.peak_len.max(self.len);
+                self.epoch = self.epoch.wrapping_add(1);
+
+                Ok(())
+            }
+            _ => Err(value),
+        }
+    }
+
+    /// Reserve `count` placeholders, pass their indices to `produce`, then
+    /// fill the placeholders in order with the values it yields. Returns the
+    /// new indices.
+    ///
+    /// Since every index is known before any value is made, values can refer
+    /// to each other, like the nodes of a cyclic graph.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `produce` doesn't yield exactly `count` values. The
+    /// placeholders are released first, so the arena is left without them.
+    pub fn reserve_then_fill<I, F>(&mut self, count: usize, produce: F) -> Vec<Index>
+    where
+        I: IntoIterator<Item = T>,
+        F: FnOnce(&[Index]) -> I,
+    {
+        let indices: Vec<Index> = (0..count).map(|_| self.reserve_placeholder()).collect();
+        let values: Vec<T> = produce(&indices).into_iter().collect();
+
+        if values.len() != count {
+            // Releasing in reverse puts the free list back in its old order.
+            for &index in indices.iter().rev() {
+                self.release_placeholder(index);
+            }
+
+            panic!(
+                "reserve_then_fill expected {} values, but got {}",
+                count,
+                values.len()
+            );
+        }
+
+        for (&index, value) in indices.iter().zip(values) {
+            if self.fulfill(index, value).is_err() {
+                unreachable!("placeholder was reserved above");
+            }
+        }
+
+        indices
+    }
+
+    /// Turn a placeholder back into an empty entry at the head of the free
+    /// list. Its generation is kept, so its index won't be handed out again.
+    fn release_placeholder(&mut self, index: Index) {
+        let entry = &mut self.storage[index.slot as usize];
+        debug_assert!(matches!(entry, Entry::Placeholder(_)));
+
+        *entry = Entry::Empty(EmptyEntry {
+            generation: index.generation,
+            next_free: self.first_free,
+        });
+        self.first_free = Some(FreePointer::from_slot(index.slot));
+        self.placeholders = self
+            .placeholders
+            .checked_sub(1)
+            .unwrap_or_else(|| unreachable!());
+        self.epoch = self.epoch.wrapping_add(1);
+    }
+
+    /// Write a new entry into the first free slot, or the end of storage if
+    /// there are no free slots, returning the entry's index. `make_entry` is
+    /// given the generation the new entry should have.
+    fn insert_entry<F: FnOnce(Generation) -> Entry<T>>(&mut self, make_entry: F) -> Index {
         // If there was a previously free entry, we can re-use its slot as long
         // as we increment its generation.
         if let Some(free_pointer) = self.first_free {
@@ -158,21 +1041,22 @@ impl<T> Arena<T> {
             // Overwrite the entry directly using our mutable reference instead
             // of indexing into our storage again. This should avoid an
             // additional bounds check.
-            let generation = empty.generation.next();
-            *entry = Entry::Occupied(OccupiedEntry { generation, value });
+            let generation = next_generation(self.provenance, empty.generation);
+            *entry = make_entry(generation);
+            self.epoch = self.epoch.wrapping_add(1);
 
             Index { slot, generation }
         } else {
             // There were no more empty entries left in our free list, so we'll
             // create a new first-generation entry and push it into storage.
 
-            let generation = Generation::first();
+            let generation = first_generation(self.provenance);
             let slot: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
                 unreachable!("Arena storage exceeded what can be represented by a u32")
             });
 
-            self.storage
-                .push(Entry::Occupied(OccupiedEntry { generation, value }));
+            self.storage.push(make_entry(generation));
+            self.epoch = self.epoch.wrapping_add(1);
 
             Index { slot, generation }
         }
@@ -180,15 +1064,126 @@ impl<T> Arena<T> {
 
     /// Returns true if the given index is valid for the arena.
     pub fn contains(&self, index: Index) -> bool {
-        match self.storage.get(index.slot as usize) {
-            Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => true,
-            _ => false,
+        self.get(index).is_some()
+    }
+
+    /// Returns true if every index in `sorted_indices` is valid for the arena,
+    /// stopping at the first one that isn't.
+    ///
+    /// `sorted_indices` must be sorted by slot, which `Index`'s `Ord`
+    /// implementation does, so that storage is swept once from front to back.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `sorted_indices` isn't sorted by slot.
+    pub fn contains_all_sorted(&self, sorted_indices: &[Index]) -> bool {
+        // `rest` holds the slots from `start` onwards and only ever shrinks
+        // from the front.
+        let mut rest = &self.storage[..];
+        let mut start = 0;
+
+        for &index in sorted_indices {
+            self.check_provenance(index);
+
+            let skip = index
+                .slot
+                .checked_sub(start)
+                .unwrap_or_else(|| panic!("indices must be sorted by slot"));
+            rest = match rest.get(skip as usize..) {
+                Some(rest) => rest,
+                None => return false,
+            };
+            start = index.slot;
+
+            match rest.first() {
+                Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {}
+                _ => return false,
+            }
         }
+
+        true
     }
 
-    /// Checks to see whether a slot is occupied in the arena, and if it is,
-    /// returns `Some` with the true `Index` of that slot (slot plus generation.)
-    /// Otherwise, returns `None`.
+    /// Returns the indices of all entries in this arena that are not contained
+    /// in `other`, in slot order.
+    ///
+    /// Indices are compared exactly, so an entry in a slot that `other` has
+    /// also filled will still be reported if its generation differs.
+    pub fn indices_not_in<U>(&self, other: &Arena<U>) -> Vec<Index> {
+        self.iter()
+            .map(|(index, _)| index)
+            .filter(|&index| !other.contains(index))
+            .collect()
+    }
+
+    /// Returns true if this arena and `other` contain exactly the same
+    /// indices, meaning that any index valid in one is valid in the other.
+    /// Values are not compared.
+    pub fn index_compatible_with<U>(&self, other: &Arena<U>) -> bool {
+        self.len == other.len
+            && self
+                .iter()
+                .map(|(index, _)| index)
+                .eq(other.iter().map(|(index, _)| index))
+    }
+
+    /// Returns true if any slot holds a value or placeholder in both this
+    /// arena and `other`, regardless of generation.
+    ///
+    /// Arenas that don't overlap can be merged with
+    /// [`Arena::merge_preserving`] without moving any values, so every index
+    /// into either arena keeps its slot.
+    pub fn slots_overlap<U>(&self, other: &Arena<U>) -> bool {
+        self.first_overlapping_slot(other).is_some()
+    }
+
+    /// Returns the first slot that holds a value or placeholder in both this
+    /// arena and `other`.
+    fn first_overlapping_slot<U>(&self, other: &Arena<U>) -> Option<u32> {
+        (0u32..)
+            .zip(self.storage.iter().zip(other.storage.iter()))
+            .find(|(_, (ours, theirs))| {
+                ours.live_generation().is_some() && theirs.live_generation().is_some()
+            })
+            .map(|(slot, _)| slot)
+    }
+
+    /// Returns true if the given index refers to a slot that exists in the
+    /// arena, but is no longer valid because its value was removed or the slot
+    /// has been reused. Useful for diagnosing leaked indices.
+    ///
+    /// Indices whose slot is past the end of the arena's storage are not
+    /// considered dangling.
+    pub fn is_dangling(&self, index: Index) -> bool {
+        (index.slot as usize) < self.storage.len() && !self.contains(index)
+    }
+
+    /// Gather diagnostic information about the slot that `index` points to,
+    /// like what value now lives there and how many generations have passed
+    /// since `index` was issued. Useful for investigating stale indices.
+    pub fn forensics(&self, index: Index) -> SlotForensics<'_, T> {
+        self.check_provenance(index);
+
+        let (generation, occupant) = match self.storage.get(index.slot as usize) {
+            Some(Entry::Occupied(occupied)) => (Some(occupied.generation), Some(&occupied.value)),
+            Some(Entry::Placeholder(generation)) => (Some(*generation), None),
+            Some(Entry::Empty(empty)) => (Some(empty.generation), None),
+            None => (None, None),
+        };
+
+        let current_generation = generation.map(Generation::to_u32);
+
+        SlotForensics {
+            current_generation,
+            generation_gap: current_generation
+                .map(|current| current.wrapping_sub(index.generation.to_u32())),
+            occupant,
+        }
+    }
+
+    /// Checks to see whether a slot is occupied in the arena, and if it is,
+    /// returns `Some` with the true `Index` of that slot (slot plus generation.)
+    /// Otherwise, returns `None`.
     pub fn contains_slot(&self, slot: u32) -> Option<Index> {
         match self.storage.get(slot as usize) {
             Some(Entry::Occupied(occupied)) => Some(Index {
@@ -202,6 +1197,8 @@ impl<T> Arena<T> {
     /// Get an immutable reference to a value inside the arena by
     /// [`Index`], returning `None` if the index is not contained in the arena.
     pub fn get(&self, index: Index) -> Option<&T> {
+        self.check_provenance(index);
+
         match self.storage.get(index.slot as usize) {
             Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {
                 Some(&occupied.value)
@@ -213,6 +1210,8 @@ impl<T> Arena<T> {
     /// Get a mutable reference to a value inside the arena by [`Index`],
     /// returning `None` if the index is not contained in the arena.
     pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        self.check_provenance(index);
+
         match self.storage.get_mut(index.slot as usize) {
             Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {
                 Some(&mut occupied.value)
@@ -221,9 +1220,387 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Get a mutable reference to a value inside the arena by [`Index`] along
+    /// with the arena's length, returning `None` if the index is not
+    /// contained in the arena.
+    pub fn get_mut_and_len(&mut self, index: Index) -> Option<(&mut T, usize)> {
+        let len = self.len();
+        self.get_mut(index).map(|value| (value, len))
+    }
+
+    /// Get an immutable reference to a value inside the arena by an index
+    /// converted with [`Index::to_bits`], returning `None` if the bits don't
+    /// describe an index or the index is not contained in the arena.
+    pub fn get_by_bits(&self, bits: u64) -> Option<&T> {
+        self.get(Index::try_from_bits(bits)?)
+    }
+
+    /// Get a mutable reference to a value inside the arena by an index
+    /// converted with [`Index::to_bits`], returning `None` if the bits don't
+    /// describe an index or the index is not contained in the arena.
+    pub fn get_by_bits_mut(&mut self, bits: u64) -> Option<&mut T> {
+        self.get_mut(Index::try_from_bits(bits)?)
+    }
+
+    /// Check that the given index is contained in the arena, returning a
+    /// token that can be used to access its value later with
+    /// [`Arena::apply_resolved_mut`].
+    ///
+    /// Useful for command buffers, which can reject bad indices while
+    /// recording commands instead of while applying them.
+    pub fn resolve(&self, index: Index) -> Option<ResolvedRef> {
+        if self.contains(index) {
+            Some(ResolvedRef {
+                slot: index.slot,
+                generation: index.generation,
+            })
+        } else {
+            None
+        }
+    }
+
+    /// Get a mutable reference to the value a token from [`Arena::resolve`]
+    /// refers to, returning `None` if the value has since been removed or its
+    /// index invalidated.
+    pub fn apply_resolved_mut(&mut self, resolved: ResolvedRef) -> Option<&mut T> {
+        self.check_provenance(resolved.index());
+
+        match self.storage.get_mut(resolved.slot as usize) {
+            Some(Entry::Occupied(occupied)) if occupied.generation == resolved.generation => {
+                Some(&mut occupied.value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Call `f` with a mutable reference to the value at the given index,
+    /// returning its result, or `None` if the index is not contained in the
+    /// arena.
+    ///
+    /// The mutable borrow can't outlive the closure, which makes it harder to
+    /// accidentally hold onto it across other calls into the arena.
+    pub fn with_mut<R, F: FnOnce(&mut T) -> R>(&mut self, index: Index, f: F) -> Option<R> {
+        self.get_mut(index).map(f)
+    }
+
+    /// Start assigning entity ids to values as they're inserted.
+    ///
+    /// Unlike an [`Index`], whose slot is reused once its value is removed, an
+    /// entity id is never handed out twice by the same arena, which makes it
+    /// suitable for referring to values across sessions. Values inserted
+    /// before this is called don't have an entity id.
+    pub fn enable_entity_ids(&mut self) {
+        if self.next_entity_id.is_none() {
+            self.next_entity_id = NonZeroU64::new(1);
+        }
+    }
+
+    /// Returns the entity id of the value at the given index, if the index is
+    /// contained in the arena and the value was assigned one. See
+    /// [`Arena::enable_entity_ids`].
+    pub fn entity_id(&self, index: Index) -> Option<u64> {
+        self.get(index)?;
+        self.entity_ids.get(index.slot).map(NonZeroU64::get)
+    }
+
+    /// Find the index of the value with the given entity id, returning `None`
+    /// if no value in the arena has that id.
+    ///
+    /// This scans the whole arena.
+    pub fn find_by_entity_id(&self, id: u64) -> Option<Index> {
+        self.iter()
+            .map(|(index, _)| index)
+            .find(|index| self.entity_ids.get(index.slot).map(NonZeroU64::get) == Some(id))
+    }
+
+    /// Get an immutable reference to a value inside the arena by [`Index`]
+    /// along with the generation of its slot, returning `None` if the index is
+    /// not contained in the arena.
+    pub fn get_with_generation(&self, index: Index) -> Option<(u32, &T)> {
+        self.check_provenance(index);
+
+        match self.storage.get(index.slot as usize) {
+            Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {
+                Some((occupied.generation.to_u32(), &occupied.value))
+            }
+            _ => None,
+        }
+    }
+
+    /// Get an immutable reference to a value inside the arena by [`Index`]
+    /// along with the arena's current [epoch](Arena::epoch), returning `None`
+    /// if the index is not contained in the arena.
+    pub fn get_with_epoch(&self, index: Index) -> Option<(&T, u64)> {
+        self.get(index).map(|value| (value, self.epoch))
+    }
+
+    /// Get an immutable reference to a value inside the arena by [`Index`]
+    /// along with the entry's version, returning `None` if the index is not
+    /// contained in the arena.
+    ///
+    /// Versions start at `0` when a value is inserted, and are advanced each
+    /// time the value is replaced with [`Arena::replace_if_version`].
+    pub fn get_versioned(&self, index: Index) -> Option<(&T, u64)> {
+        self.get(index)
+            .map(|value| (value, self.versions.get(index.slot)))
+    }
+
+    /// Replace the value at the given index only if the entry's version
+    /// matches `expected_version`, returning the entry's new version.
+    ///
+    /// If the index is not contained in the arena or the versions don't match,
+    /// the arena is left untouched and `value` is dropped.
+    pub fn replace_if_version(
+        &mut self,
+        index: Index,
+        expected_version: u64,
+        value: T,
+    ) -> Result<u64, VersionError> {
+        self.check_provenance(index);
+
+        match self.storage.get_mut(index.slot as usize) {
+            Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {
+                let current = self.versions.get(index.slot);
+                if current != expected_version {
+                    return Err(VersionError::Mismatch { current });
+                }
+
+                let version = current.wrapping_add(1);
+                self.versions.set(index.slot, version);
+                occupied.value = value;
+
+                Ok(version)
+            }
+            _ => Err(VersionError::Missing),
+        }
+    }
+
+    /// Get mutable references to two distinct values inside the arena at once,
+    /// returning an error describing which index was the problem if either
+    /// index is not contained in the arena or both refer to the same entry.
+    pub fn get2_mut_detailed(&mut self, a: Index, b: Index) -> Result<(&mut T, &mut T), Get2Error> {
+        match (self.contains(a), self.contains(b)) {
+            (false, false) => return Err(Get2Error::BothMissing),
+            (false, true) => return Err(Get2Error::FirstMissing),
+            (true, false) => return Err(Get2Error::SecondMissing),
+            (true, true) => {}
+        }
+
+        // Two valid indices with the same slot must be the same index.
+        if a.slot == b.slot {
+            return Err(Get2Error::Alias);
+        }
+
+        let (a_entry, b_entry) = if a.slot < b.slot {
+            let (front, back) = self.storage.split_at_mut(b.slot as usize);
+            (&mut front[a.slot as usize], &mut back[0])
+        } else {
+            let (front, back) = self.storage.split_at_mut(a.slot as usize);
+            (&mut back[0], &mut front[b.slot as usize])
+        };
+
+        match (a_entry, b_entry) {
+            (Entry::Occupied(a_occupied), Entry::Occupied(b_occupied)) => {
+                Ok((&mut a_occupied.value, &mut b_occupied.value))
+            }
+            _ => unreachable!("entries were checked to be occupied"),
+        }
+    }
+
+    /// Get mutable references to two distinct values inside the arena along
+    /// with a read-only view over every other value, returning `None` if
+    /// either index is not contained in the arena.
+    ///
+    /// The view refuses to return `a` or `b`. Useful for algorithms that update
+    /// two entries while reading the structure around them.
+    ///
+    /// # Panics
+    /// Panics if `a` and `b` refer to the same entry.
+    pub fn get2_mut_and_rest(
+        &mut self,
+        a: Index,
+        b: Index,
+    ) -> Option<(&mut T, &mut T, ArenaRest<'_, T>)> {
+        if !self.contains(a) || !self.contains(b) {
+            return None;
+        }
+
+        // Two valid indices with the same slot must be the same index.
+        assert!(a.slot != b.slot, "Index {:?} was given twice", a);
+
+        let (first, second) = if a.slot < b.slot {
+            (a.slot, b.slot)
+        } else {
+            (b.slot, a.slot)
+        };
+
+        let (front, rest) = self.storage.split_at_mut(first as usize);
+        let (first_entry, rest) = rest
+            .split_first_mut()
+            .unwrap_or_else(|| unreachable!("index was checked to be contained"));
+        let gap = second
+            .checked_sub(first)
+            .and_then(|gap| gap.checked_sub(1))
+            .unwrap_or_else(|| unreachable!());
+        let (middle, rest) = rest.split_at_mut(gap as usize);
+        let (second_entry, back) = rest
+            .split_first_mut()
+            .unwrap_or_else(|| unreachable!("index was checked to be contained"));
+
+        let rest = ArenaRest {
+            front,
+            middle,
+            back,
+            first,
+            second,
+            provenance: self.provenance,
+        };
+
+        match (first_entry, second_entry) {
+            (Entry::Occupied(first_occupied), Entry::Occupied(second_occupied)) => {
+                if a.slot == first {
+                    Some((&mut first_occupied.value, &mut second_occupied.value, rest))
+                } else {
+                    Some((&mut second_occupied.value, &mut first_occupied.value, rest))
+                }
+            }
+            _ => unreachable!("entries were checked to be occupied"),
+        }
+    }
+
+    /// Get a mutable reference to the value at `target` along with immutable
+    /// references to the values at each of `neighbors`, returning `None` if
+    /// `target` is not contained in the arena.
+    ///
+    /// Neighbors that are not contained in the arena resolve to `None`.
+    ///
+    /// # Panics
+    /// Panics if any of `neighbors` refers to the same entry as `target`.
+    pub fn get_mut_and_refs<const N: usize>(
+        &mut self,
+        target: Index,
+        neighbors: [Index; N],
+    ) -> Option<(&mut T, [Option<&T>; N])> {
+        if !self.contains(target) {
+            return None;
+        }
+
+        for neighbor in &neighbors {
+            self.check_provenance(*neighbor);
+            assert!(
+                *neighbor != target,
+                "Neighbor {:?} aliases the target entry",
+                neighbor
+            );
+        }
+
+        let (front, rest) = self.storage.split_at_mut(target.slot as usize);
+        let (target_entry, back) = rest
+            .split_first_mut()
+            .unwrap_or_else(|| unreachable!("target was checked to be contained"));
+        let front: &[Entry<T>] = front;
+        let back: &[Entry<T>] = back;
+
+        let mut refs = [None; N];
+        for (neighbor, slot) in neighbors.iter().zip(refs.iter_mut()) {
+            let entry = match neighbor.slot.cmp(&target.slot) {
+                Ordering::Less => front.get(neighbor.slot as usize),
+                Ordering::Greater => (neighbor.slot as usize)
+                    .checked_sub(target.slot as usize)
+                    .and_then(|offset| offset.checked_sub(1))
+                    .and_then(|offset| back.get(offset)),
+                // A neighbor with the target's slot but a different generation
+                // is stale.
+                Ordering::Equal => None,
+            };
+
+            *slot = match entry {
+                Some(Entry::Occupied(occupied)) if occupied.generation == neighbor.generation => {
+                    Some(&occupied.value)
+                }
+                _ => None,
+            };
+        }
+
+        match target_entry {
+            Entry::Occupied(occupied) => Some((&mut occupied.value, refs)),
+            _ => unreachable!("target was checked to be contained"),
+        }
+    }
+
+    /// Get mutable references to the values at each of `indices` at once.
+    /// Indices that are not contained in the arena resolve to `None`.
+    ///
+    /// Only indices that are contained in the arena can alias each other. A
+    /// stale index that shares a slot with a valid index resolves to `None`
+    /// instead of aliasing, just like [`Arena::get2_mut_detailed`] reports it
+    /// as missing.
+    ///
+    /// # Panics
+    /// Panics if the same valid index is given more than once.
+    pub fn get_disjoint_mut<const N: usize>(&mut self, indices: [Index; N]) -> [Option<&mut T>; N] {
+        let mut wanted: Vec<(u32, usize)> = Vec::with_capacity(N);
+        for (position, &index) in indices.iter().enumerate() {
+            if self.contains(index) {
+                wanted.push((index.slot, position));
+            }
+        }
+        wanted.sort_unstable();
+
+        for pair in wanted.windows(2) {
+            assert!(
+                pair[0].0 != pair[1].0,
+                "Index {:?} was given more than once",
+                indices[pair[0].1]
+            );
+        }
+
+        let mut results: Vec<Option<&mut T>> = (0..N).map(|_| None).collect();
+        let mut rest: &mut [Entry<T>] = &mut self.storage;
+        let mut base = 0u32;
+
+        for (slot, position) in wanted {
+            let offset = slot.checked_sub(base).unwrap_or_else(|| unreachable!());
+            let (_, tail) = take(&mut rest).split_at_mut(offset as usize);
+            let (entry, tail) = tail
+                .split_first_mut()
+                .unwrap_or_else(|| unreachable!("index was checked to be contained"));
+
+            rest = tail;
+            base = slot.checked_add(1).unwrap_or_else(|| unreachable!());
+
+            match entry {
+                Entry::Occupied(occupied) => results[position] = Some(&mut occupied.value),
+                _ => unreachable!("index was checked to be contained"),
+            }
+        }
+
+        results
+            .try_into()
+            .unwrap_or_else(|_| unreachable!("results has exactly N elements"))
+    }
+
+    /// Get a clone of a value inside the arena by [`Index`], returning `None`
+    /// if the index is not contained in the arena.
+    pub fn get_cloned(&self, index: Index) -> Option<T>
+    where
+        T: Clone,
+    {
+        self.get(index).cloned()
+    }
+
+    /// Get immutable references to the values at each of the given indices,
+    /// in order. Each element is `None` if its index is not contained in the
+    /// arena. Repeated indices are allowed.
+    pub fn get_many(&self, indices: &[Index]) -> Vec<Option<&T>> {
+        indices.iter().map(|&index| self.get(index)).collect()
+    }
+
     /// Remove the value contained at the given index from the arena, returning
     /// it if it was present.
     pub fn remove(&mut self, index: Index) -> Option<T> {
+        self.check_provenance(index);
+
         let entry = self.storage.get_mut(index.slot as usize)?;
 
         match entry {
@@ -248,6 +1625,9 @@ impl<T> Arena<T> {
                 self.first_free = Some(FreePointer::from_slot(index.slot));
 
                 self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+                self.hooks.observe(index.slot, index.generation);
+                self.epoch = self.epoch.wrapping_add(1);
+                self.auto_trim_after_remove(index.slot);
 
                 Some(value)
             }
@@ -255,15 +1635,63 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Remove the value contained at the given index from the arena, returning
+    /// it if it was present. If the value was in the last slot of the arena's
+    /// storage, any empty slots at the end of storage are also released.
+    ///
+    /// This is a one-off version of [`Arena::set_auto_trim`], and has the same
+    /// caveats about stale indices.
+    pub fn remove_compacting(&mut self, index: Index) -> Option<T> {
+        let value = self.remove(index)?;
+
+        if index.slot as usize == self.storage.len().saturating_sub(1) {
+            self.trim_trailing_empty();
+        }
+
+        Some(value)
+    }
+
+    /// Remove the value contained at the given index from the arena, keeping
+    /// it to be reused by a later call to [`Arena::insert_recycled`]. Returns
+    /// whether the index was contained in the arena.
+    pub fn remove_recycling(&mut self, index: Index) -> bool {
+        match self.remove(index) {
+            Some(value) => {
+                self.recycled.push(value);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it along with the slot that was freed if it was present.
+    pub fn remove_returning_slot(&mut self, index: Index) -> Option<(u32, T)> {
+        self.remove(index).map(|value| (index.slot, value))
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it along with whether the arena is now empty, if it was present.
+    ///
+    /// Useful for pools that release resources once their last value is
+    /// removed.
+    pub fn remove_reporting_empty(&mut self, index: Index) -> Option<(T, bool)> {
+        self.remove(index).map(|value| (value, self.is_empty()))
+    }
+
     /// Invalidate the given index and return a new index to the same value. This
     /// is roughly equivalent to `remove` followed by `insert`, but much faster.
     /// If the old index is already invalid, this method returns `None`.
     pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        self.check_provenance(index);
+
         let entry = self.storage.get_mut(index.slot as usize)?;
 
         match entry {
             Entry::Occupied(occupied) if occupied.generation == index.generation => {
-                occupied.generation = occupied.generation.next();
+                occupied.generation = next_generation(self.provenance, occupied.generation);
+                self.hooks.observe(index.slot, index.generation);
+                self.epoch = self.epoch.wrapping_add(1);
 
                 Some(Index {
                     generation: occupied.generation,
@@ -274,6 +1702,53 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Invalidate the given index and return a new index to the same value,
+    /// like [`Arena::invalidate`], but without letting the slot's generation
+    /// wrap around.
+    ///
+    /// If the generation can't be advanced any further, the entry is left
+    /// untouched and [`InvalidateError::GenerationExhausted`] is returned,
+    /// giving the caller a chance to retire the slot.
+    pub fn try_invalidate(&mut self, index: Index) -> Result<Index, InvalidateError> {
+        self.check_provenance(index);
+
+        let entry = self
+            .storage
+            .get_mut(index.slot as usize)
+            .ok_or(InvalidateError::Missing)?;
+
+        match entry {
+            Entry::Occupied(occupied) if occupied.generation == index.generation => {
+                occupied.generation = checked_next_generation(self.provenance, occupied.generation)
+                    .ok_or(InvalidateError::GenerationExhausted)?;
+                self.hooks.observe(index.slot, index.generation);
+                self.epoch = self.epoch.wrapping_add(1);
+
+                Ok(Index {
+                    generation: occupied.generation,
+                    ..index
+                })
+            }
+            _ => Err(InvalidateError::Missing),
+        }
+    }
+
+    /// Returns true if the given slot is empty because its value was removed
+    /// and no new value has been inserted into it yet. Slots that never held a
+    /// value, like the ones [`Arena::insert_if_slot_free`] adds to reach its
+    /// slot, are not considered freed.
+    ///
+    /// Useful for diagnosing reads through [`Arena::get_by_slot`] that expected
+    /// a value to still be present. This isn't limited to debug builds like
+    /// provenance checks are, since the bookkeeping it needs is only allocated
+    /// for arenas that have padding slots.
+    pub fn was_recently_freed(&self, slot: u32) -> bool {
+        match self.storage.get(slot as usize) {
+            Some(entry) => entry.get_empty().is_some() && !self.padding.get(slot),
+            None => false,
+        }
+    }
+
     /// Attempt to look up the given slot in the arena, disregarding any generational
     /// information, and retrieve an immutable reference to it. Returns `None` if the
     /// slot is empty.
@@ -306,6 +1781,33 @@ impl<T> Arena<T> {
         }
     }
 
+    /// Swap the values stored in two slots, disregarding any generational
+    /// information. Generations stay with their slots, so each slot's index
+    /// now refers to the other slot's old value.
+    ///
+    /// Returns `false` and leaves the arena unchanged if either slot is not
+    /// occupied.
+    pub fn swap_by_slot(&mut self, a: u32, b: u32) -> bool {
+        let (low, high) = (a.min(b) as usize, a.max(b) as usize);
+
+        if high >= self.storage.len() {
+            return false;
+        }
+
+        if low == high {
+            return matches!(self.storage[low], Entry::Occupied(_));
+        }
+
+        let (front, back) = self.storage.split_at_mut(high);
+        match (&mut front[low], &mut back[0]) {
+            (Entry::Occupied(low), Entry::Occupied(high)) => {
+                swap(&mut low.value, &mut high.value);
+                true
+            }
+            _ => false,
+        }
+    }
+
     /// Remove an entry in the arena by its slot, disregarding any generational info.
     /// Returns `None` if the slot was already empty.
     pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
@@ -336,6 +1838,9 @@ impl<T> Arena<T> {
                 self.first_free = Some(FreePointer::from_slot(slot));
 
                 self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+                self.hooks.observe(slot, index.generation);
+                self.epoch = self.epoch.wrapping_add(1);
+                self.auto_trim_after_remove(slot);
 
                 Some((index, value))
             }
@@ -348,6 +1853,60 @@ impl<T> Arena<T> {
         self.drain().for_each(drop);
     }
 
+    /// Return the arena to the state of a newly constructed arena, dropping
+    /// all elements but keeping its storage allocation for reuse.
+    ///
+    /// Unlike [`Arena::clear`], every slot is released and generations start
+    /// over, so the arena hands out the same indices a new arena would.
+    /// Indices from before the reset may become valid again, so this is only
+    /// appropriate once no old indices are still in use, like when returning
+    /// an arena to a pool. Settings like auto trimming and installed hooks
+    /// are kept, and if entity ids are enabled, they start over from the
+    /// first id.
+    pub fn reset(&mut self) {
+        self.storage.clear();
+        self.tags.clear();
+        self.versions.clear();
+        self.entity_ids.clear();
+        self.padding.clear();
+        self.recycled.clear();
+        self.len = 0;
+        self.peak_len = 0;
+        self.placeholders = 0;
+        self.first_free = None;
+        self.hooks.forget_slots();
+        self.epoch = 0;
+
+        if self.next_entity_id.is_some() {
+            self.next_entity_id = NonZeroU64::new(1);
+        }
+    }
+
+    /// Remove and drop every value whose slot is within `range`. Only slots in
+    /// the range are visited.
+    pub fn clear_range<R: RangeBounds<u32>>(&mut self, range: R) {
+        let start = match range.start_bound() {
+            Bound::Included(&start) => u64::from(start),
+            Bound::Excluded(&start) => u64::from(start)
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!()),
+            Bound::Unbounded => 0,
+        };
+        let end = match range.end_bound() {
+            Bound::Included(&end) => u64::from(end)
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!()),
+            Bound::Excluded(&end) => u64::from(end),
+            Bound::Unbounded => u64::MAX,
+        };
+        let end = end.min(self.storage.len() as u64);
+
+        for slot in start..end {
+            // Slots past the end are skipped, so every slot here fits in a u32.
+            self.remove_by_slot(slot as u32);
+        }
+    }
+
     /// Iterate over all of the indexes and values contained in the arena.
     ///
     /// Iteration order is not defined.
@@ -358,252 +1917,3829 @@ impl<T> Arena<T> {
         }
     }
 
-    /// Iterate over all of the indexes and values contained in the arena, with
-    /// mutable access to each value.
+    /// Iterate over the indexes and values of all entries inserted with the
+    /// given tag via [`Arena::insert_tagged`].
     ///
     /// Iteration order is not defined.
-    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
-        IterMut {
-            inner: self.storage.iter_mut().enumerate(),
-            len: self.len,
-        }
+    pub fn iter_tagged(&self, tag: u16) -> impl Iterator<Item = (Index, &T)> {
+        self.iter()
+            .filter(move |(index, _)| self.tags.get(index.slot) == tag)
     }
 
-    /// Returns an iterator that removes each element from the arena.
+    /// Iterate over the indexes and values of all entries whose generation is
+    /// at least `min_generation`, like entries that were recently recreated.
     ///
     /// Iteration order is not defined.
+    pub fn iter_since_generation(&self, min_generation: u32) -> impl Iterator<Item = (Index, &T)> {
+        self.iter()
+            .filter(move |(index, _)| index.generation.to_u32() >= min_generation)
+    }
+
+    /// Iterate over the indexes and values of all entries whose slot matches
+    /// `value` in the bits selected by `mask`, meaning `slot & mask == value`.
     ///
-    /// If the iterator is dropped before it is fully consumed, any uniterated
-    /// items will be dropped from the arena, and the arena will be empty.
-    /// The arena's capacity will not be changed.
-    pub fn drain(&mut self) -> Drain<'_, T> {
-        Drain {
-            arena: self,
-            slot: 0,
-        }
+    /// Useful for partitioning an arena into shards by the low bits of each
+    /// slot. Iteration order is not defined.
+    pub fn iter_slot_masked(&self, mask: u32, value: u32) -> impl Iterator<Item = (Index, &T)> {
+        self.iter()
+            .filter(move |(index, _)| index.slot & mask == value)
     }
 
-    /// Remove all entries in the `Arena` which don't satisfy the provided predicate.
-    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
-        for (i, entry) in self.storage.iter_mut().enumerate() {
-            if let Entry::Occupied(occupied) = entry {
-                let index = Index {
-                    slot: i as u32,
-                    generation: occupied.generation,
-                };
+    /// Iterate over the indexes and values of all entries that satisfy `pred`.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_filter<F: FnMut(Index, &T) -> bool>(
+        &self,
+        mut pred: F,
+    ) -> impl Iterator<Item = (Index, &T)> {
+        self.iter()
+            .filter(move |&(index, value)| pred(index, value))
+    }
 
-                if !f(index, &mut occupied.value) {
-                    // We can replace an occupied entry with an empty entry with the
-                    // same generation. On next insertion, this generation will
-                    // increment.
-                    *entry = Entry::Empty(EmptyEntry {
-                        generation: occupied.generation,
-                        next_free: self.first_free,
-                    });
+    /// Iterate over all of the values contained in the arena along with their
+    /// indices converted with [`Index::to_bits`].
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_bits(&self) -> impl Iterator<Item = (u64, &T)> {
+        self.iter().map(|(index, value)| (index.to_bits(), value))
+    }
 
-                    // The next time we insert, we can re-use the empty entry we
-                    // just created. If another removal happens before then, that
-                    // entry will be used before this one (FILO).
-                    self.first_free = Some(FreePointer::from_slot(index.slot));
+    /// Clone every value in the arena into a list sorted by `cmp`, along with
+    /// a parallel list giving the index of each sorted value.
+    ///
+    /// The sort is stable, so values that compare equal stay in slot order.
+    pub fn to_sorted_vec_by<F: FnMut(&T, &T) -> Ordering>(&self, mut cmp: F) -> (Vec<T>, Vec<Index>)
+    where
+        T: Clone,
+    {
+        let mut entries: Vec<(Index, &T)> = self.iter().collect();
+        entries.sort_by(|a, b| cmp(a.1, b.1));
 
-                    // We just verified that this entry is (was) occupied, so there's
-                    // trivially no way for this `checked_sub` to fail.
-                    self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
-                }
-            }
-        }
+        entries
+            .into_iter()
+            .map(|(index, value)| (value.clone(), index))
+            .unzip()
     }
-}
 
-impl<T> Default for Arena<T> {
-    fn default() -> Self {
-        Arena::new()
+    /// Iterate over the entries at the given indices, in the given order,
+    /// skipping any index that is not contained in the arena.
+    pub fn iter_ordered<'a, I>(&'a self, order: I) -> impl Iterator<Item = (Index, &'a T)> + 'a
+    where
+        I: IntoIterator<Item = Index>,
+        I::IntoIter: 'a,
+    {
+        order
+            .into_iter()
+            .filter_map(move |index| self.get(index).map(|value| (index, value)))
     }
-}
 
-impl<T> IntoIterator for Arena<T> {
-    type Item = (Index, T);
-    type IntoIter = IntoIter<T>;
+    /// Return the indices of every entry whose value satisfies `pred`, in slot
+    /// order.
+    pub fn indices_where<F: FnMut(Index, &T) -> bool>(&self, mut pred: F) -> Vec<Index> {
+        self.iter()
+            .filter(|&(index, value)| pred(index, value))
+            .map(|(index, _)| index)
+            .collect()
+    }
 
-    fn into_iter(self) -> Self::IntoIter {
-        IntoIter {
+    /// Collect references to every value in the arena into a dense list in
+    /// slot order, along with a table mapping each slot to its value's
+    /// position in that list, or `None` if the slot holds no value.
+    ///
+    /// Useful for uploading values to a packed buffer, like a GPU buffer, along
+    /// with an indirection table.
+    pub fn pack_values(&self) -> (Vec<&T>, Vec<Option<u32>>) {
+        let mut values = Vec::with_capacity(self.len());
+        let mut table = Vec::with_capacity(self.storage.len());
+
+        for entry in &self.storage {
+            match entry {
+                Entry::Occupied(occupied) => {
+                    let dense = values.len().try_into().unwrap_or_else(|_| {
+                        unreachable!("Arena length exceeded what can be represented by a u32")
+                    });
+
+                    table.push(Some(dense));
+                    values.push(&occupied.value);
+                }
+                Entry::Placeholder(_) | Entry::Empty(_) => table.push(None),
+            }
+        }
+
+        (values, table)
+    }
+
+    /// Returns the `k` greatest entries in the arena according to `cmp`, from
+    /// greatest to least. If `k` is at least the arena's length, every entry is
+    /// returned.
+    ///
+    /// Entries are streamed through a heap holding at most `k` of them, so
+    /// only O(k) extra memory is used no matter how large the arena is.
+    pub fn top_k_by<F: FnMut(&T, &T) -> Ordering>(&self, k: usize, cmp: F) -> Vec<(Index, &T)> {
+        if k == 0 {
+            return Vec::new();
+        }
+
+        let cmp = RefCell::new(cmp);
+        let mut heap = BinaryHeap::with_capacity(k.min(self.len()));
+
+        for (index, value) in self.iter() {
+            let entry = TopKEntry {
+                index,
+                value,
+                cmp: &cmp,
+            };
+
+            if heap.len() < k {
+                heap.push(entry);
+            } else if let Some(mut least) = heap.peek_mut() {
+                // The heap is ordered in reverse, so its top is the least entry
+                // kept so far, and "less" here means greater according to `cmp`.
+                if entry < *least {
+                    *least = entry;
+                }
+            }
+        }
+
+        heap.into_sorted_vec()
+            .into_iter()
+            .map(|entry| (entry.index, entry.value))
+            .collect()
+    }
+
+    /// Split the arena into two iterators, the first covering slots before
+    /// `slot` and the second covering `slot` and everything after it. Useful
+    /// for manually handing out halves of an arena to different threads.
+    ///
+    /// Each iterator visits its entries in slot order.
+    pub fn split_at_slot(
+        &self,
+        slot: u32,
+    ) -> (
+        impl Iterator<Item = (Index, &T)>,
+        impl Iterator<Item = (Index, &T)>,
+    ) {
+        let mid = (slot as usize).min(self.storage.len());
+        let (front, back) = self.storage.split_at(mid);
+
+        (occupied_entries(front, 0), occupied_entries(back, mid))
+    }
+
+    /// Split the arena into two iterators with mutable access to each value,
+    /// the first covering slots before `slot` and the second covering `slot`
+    /// and everything after it.
+    ///
+    /// Each iterator visits its entries in slot order.
+    pub fn split_at_slot_mut(
+        &mut self,
+        slot: u32,
+    ) -> (
+        impl Iterator<Item = (Index, &mut T)>,
+        impl Iterator<Item = (Index, &mut T)>,
+    ) {
+        let mid = (slot as usize).min(self.storage.len());
+        let (front, back) = self.storage.split_at_mut(mid);
+
+        (
+            occupied_entries_mut(front, 0),
+            occupied_entries_mut(back, mid),
+        )
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena, with
+    /// mutable access to each value.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_mut(&mut self) -> IterMut<'_, T> {
+        IterMut {
+            inner: self.storage.iter_mut().enumerate(),
+            len: self.len,
+        }
+    }
+
+    /// Iterate over the indices and values of every entry at or after the
+    /// given slot, in ascending slot order, starting from the beginning of the
+    /// arena if `resume` is `None`.
+    ///
+    /// Useful for spreading work over several passes: store the slot of the
+    /// last entry processed plus one, and pass it back in on the next pass.
+    pub fn iter_mut_from(&mut self, resume: Option<u32>) -> impl Iterator<Item = (Index, &mut T)> {
+        let start = (resume.unwrap_or(0) as usize).min(self.storage.len());
+        occupied_entries_mut(&mut self.storage[start..], start)
+    }
+
+    /// Visit every entry in slot order with mutable access to its value,
+    /// threading an accumulator through each call to `f` and returning its
+    /// final value.
+    pub fn fold_mut<A, F: FnMut(A, Index, &mut T) -> A>(&mut self, init: A, mut f: F) -> A {
+        self.iter_mut()
+            .fold(init, |acc, (index, value)| f(acc, index, value))
+    }
+
+    /// Call `f` with the index of and mutable access to every value in the
+    /// arena, in slot order.
+    ///
+    /// This is convenient for fixing up indices stored inside values after
+    /// compacting the arena with [`Arena::gc`].
+    pub fn apply_to_all<F: FnMut(Index, &mut T)>(&mut self, mut f: F) {
+        for (index, value) in self.iter_mut() {
+            f(index, value);
+        }
+    }
+
+    /// Overwrite every value in the arena with a clone of `template`. Indices
+    /// are unaffected and the old values are dropped.
+    pub fn fill(&mut self, template: T)
+    where
+        T: Clone,
+    {
+        for (_, value) in self.iter_mut() {
+            *value = template.clone();
+        }
+    }
+
+    /// Replace the value of every entry that satisfies `pred` with the result
+    /// of calling `make` with its index and old value, returning the number of
+    /// values replaced. Indices are unaffected and the old values are dropped.
+    pub fn replace_where<P, F>(&mut self, mut pred: P, mut make: F) -> usize
+    where
+        P: FnMut(&T) -> bool,
+        F: FnMut(Index, &T) -> T,
+    {
+        let mut replaced = 0usize;
+
+        for (index, value) in self.iter_mut() {
+            if pred(value) {
+                *value = make(index, value);
+                replaced = replaced.checked_add(1).unwrap_or_else(|| unreachable!());
+            }
+        }
+
+        replaced
+    }
+
+    /// Returns an iterator that removes each element from the arena.
+    ///
+    /// Iteration order is not defined.
+    ///
+    /// If the iterator is dropped before it is fully consumed, any uniterated
+    /// items will be dropped from the arena, and the arena will be empty.
+    /// The arena's capacity will not be changed.
+    pub fn drain(&mut self) -> Drain<'_, T> {
+        Drain {
+            arena: self,
+            slot: 0,
+            resize_to: None,
+        }
+    }
+
+    /// Like [`Arena::drain`], but once the iterator is fully consumed or
+    /// dropped, the arena's capacity is set to `target_capacity`.
+    ///
+    /// Useful for resetting a pool to a known size. If the arena's storage had
+    /// grown past `target_capacity`, the extra slots are released, and stale
+    /// indices pointing at them may become valid again once the arena grows
+    /// back over those slots.
+    pub fn drain_and_resize(&mut self, target_capacity: usize) -> Drain<'_, T> {
+        Drain {
             arena: self,
             slot: 0,
+            resize_to: Some(target_capacity),
+        }
+    }
+
+    /// Set the capacity of an empty arena's storage to `target_capacity`,
+    /// releasing any slots past it.
+    pub(crate) fn resize_empty(&mut self, target_capacity: usize) {
+        debug_assert!(self.is_empty());
+
+        if self.storage.len() > target_capacity {
+            let remaining: Vec<u32> = self
+                .free_list_snapshot()
+                .into_iter()
+                .filter(|&slot| (slot as usize) < target_capacity)
+                .collect();
+
+            self.truncate_storage(target_capacity);
+            self.relink_free_list(&remaining);
+            self.placeholders = count_placeholders(&self.storage);
+        }
+
+        let mut storage = Vec::with_capacity(target_capacity);
+        storage.append(&mut self.storage);
+        self.storage = storage;
+    }
+
+    /// Returns a builder that stages insertions and removals, then applies
+    /// them to the arena all at once.
+    ///
+    /// If the builder is dropped without calling
+    /// [`BatchOps::apply`](crate::BatchOps::apply), the arena is unchanged.
+    pub fn batch(&mut self) -> BatchOps<'_, T> {
+        BatchOps {
+            arena: self,
+            ops: Vec::new(),
+        }
+    }
+
+    /// Remove all entries in the `Arena` which don't satisfy the provided predicate.
+    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
+        for (i, entry) in self.storage.iter_mut().enumerate() {
+            if let Entry::Occupied(occupied) = entry {
+                let index = Index {
+                    slot: i as u32,
+                    generation: occupied.generation,
+                };
+
+                if !f(index, &mut occupied.value) {
+                    // We can replace an occupied entry with an empty entry with the
+                    // same generation. On next insertion, this generation will
+                    // increment.
+                    *entry = Entry::Empty(EmptyEntry {
+                        generation: occupied.generation,
+                        next_free: self.first_free,
+                    });
+
+                    // The next time we insert, we can re-use the empty entry we
+                    // just created. If another removal happens before then, that
+                    // entry will be used before this one (FILO).
+                    self.first_free = Some(FreePointer::from_slot(index.slot));
+
+                    // We just verified that this entry is (was) occupied, so there's
+                    // trivially no way for this `checked_sub` to fail.
+                    self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+                    self.hooks.observe(index.slot, index.generation);
+                    self.epoch = self.epoch.wrapping_add(1);
+                }
+            }
+        }
+    }
+
+    /// Remove all entries in the `Arena` which don't satisfy the provided
+    /// predicate, letting it push new values into a buffer along the way.
+    /// Once every entry has been visited, the buffered values are inserted
+    /// and their indices are returned in the order they were pushed.
+    ///
+    /// Useful for replacing removed values, like dropping loot when an enemy
+    /// is destroyed, which can't be inserted while the arena is borrowed.
+    pub fn retain_spawning<F: FnMut(Index, &mut T, &mut Vec<T>) -> bool>(
+        &mut self,
+        mut f: F,
+    ) -> Vec<Index> {
+        let mut spawned = Vec::new();
+        self.retain(|index, value| f(index, value, &mut spawned));
+
+        spawned
+            .into_iter()
+            .map(|value| self.insert(value))
+            .collect()
+    }
+
+    /// Remove all entries in the `Arena` which don't satisfy the provided
+    /// predicate, visiting entries in order of the key computed by `key`
+    /// instead of slot order.
+    ///
+    /// Entries with equal keys are visited in slot order. Useful when `keep`
+    /// has side effects whose order matters.
+    pub fn retain_ordered_by<K, G, F>(&mut self, key: G, mut keep: F)
+    where
+        K: Ord,
+        G: Fn(&T) -> K,
+        F: FnMut(Index, &mut T) -> bool,
+    {
+        let mut order: Vec<(K, Index)> = self
+            .iter()
+            .map(|(index, value)| (key(value), index))
+            .collect();
+        order.sort_by(|a, b| a.0.cmp(&b.0));
+
+        for (_, index) in order {
+            let value = self
+                .get_mut(index)
+                .unwrap_or_else(|| unreachable!("entry disappeared during retain"));
+
+            if !keep(index, value) {
+                self.remove(index);
+            }
+        }
+    }
+
+    /// Visit every entry with mutable access to its value, removing each entry
+    /// for which `f` returns [`Cull::Remove`]. Returns the number of entries
+    /// that were removed.
+    pub fn iter_mut_cull<F: FnMut(Index, &mut T) -> Cull>(&mut self, mut f: F) -> usize {
+        let mut removed = 0usize;
+
+        self.retain(|index, value| match f(index, value) {
+            Cull::Keep => true,
+            Cull::Remove => {
+                removed = removed.checked_add(1).unwrap_or_else(|| unreachable!());
+                false
+            }
+        });
+
+        removed
+    }
+
+    /// Remove all entries in the `Arena` which satisfy the provided predicate,
+    /// dropping their values and returning their indices in slot order.
+    pub fn remove_where_indices<F: FnMut(Index, &T) -> bool>(&mut self, mut pred: F) -> Vec<Index> {
+        let mut removed = Vec::new();
+
+        self.retain(|index, value| {
+            if pred(index, value) {
+                removed.push(index);
+                false
+            } else {
+                true
+            }
+        });
+
+        removed
+    }
+
+    /// Remove every live index in `marks` from the arena, dropping their
+    /// values, then clear `marks` so it can be reused. Returns the number of
+    /// values that were removed.
+    ///
+    /// Marked indices that are no longer contained in the arena, including
+    /// repeated marks, are skipped.
+    pub fn sweep_marked(&mut self, marks: &mut MarkSet) -> usize {
+        let mut removed = 0usize;
+
+        for index in marks.indices.drain(..) {
+            if self.remove(index).is_some() {
+                removed = removed.checked_add(1).unwrap_or_else(|| unreachable!());
+            }
+        }
+
+        removed
+    }
+
+    /// Compact all entries to the front of the arena and release any unused
+    /// storage, returning a map from every index that was valid before the
+    /// call to the index now referring to the same value.
+    ///
+    /// Entries that are moved receive a new generation, so indices that aren't
+    /// passed through the returned map should be considered invalid. Indices
+    /// pointing past the end of the compacted storage may become valid again
+    /// once the arena grows back over those slots.
+    ///
+    /// Placeholders created with [`Arena::reserve_placeholder`] are compacted
+    /// and included in the map as well.
+    pub fn gc(&mut self) -> HashMap<Index, Index> {
+        let unmoved: Vec<Index> = self.live_indices().collect();
+        let mut remap = HashMap::with_capacity(unmoved.len());
+
+        self.compact_and_shrink(|old, new| {
+            remap.insert(old, new);
+        });
+
+        for index in unmoved {
+            remap.entry(index).or_insert(index);
+        }
+
+        remap
+    }
+
+    /// Compact all entries to the front of the arena and reset the free list,
+    /// calling `on_move` with the old and new index of each entry that was
+    /// relocated. Entries are relocated in no particular order.
+    ///
+    /// Unlike [`Arena::compact_and_shrink`], the arena's capacity is left
+    /// unchanged. Indices of moved entries that aren't updated should be
+    /// considered invalid.
+    pub fn defragment_with<F: FnMut(Index, Index)>(&mut self, on_move: F) {
+        self.defragment_inner(on_move);
+    }
+
+    /// Compact all entries to the front of the arena, release any unused
+    /// storage, and reset the free list, calling `remap` with the old and new
+    /// index of each entry that was moved.
+    ///
+    /// This is the same operation as [`Arena::gc`], but reports moves through
+    /// a callback instead of allocating a map. Indices of moved entries that
+    /// aren't updated should be considered invalid.
+    pub fn compact_and_shrink<F: FnMut(Index, Index)>(&mut self, remap: F) {
+        self.defragment_inner(remap);
+        self.shrink_storage();
+    }
+
+    /// Release any empty slots at the end of the arena's storage and shrink its
+    /// capacity to fit, returning whether any slots were released.
+    ///
+    /// Unlike [`Arena::gc`], occupied entries are never moved, so every index
+    /// that was valid before this call remains valid. Stale indices pointing at
+    /// released slots may become valid again once the arena grows back over
+    /// those slots.
+    pub fn shrink_to_fit_safe(&mut self) -> bool {
+        let trimmed = self.trim_trailing_empty();
+        self.shrink_storage();
+        trimmed
+    }
+
+    /// Perform [`Arena::shrink_to_fit_safe`], then rebuild the remaining free
+    /// list with [`Arena::rebuild_free_list_ascending`] so that future
+    /// insertions fill the lowest empty slots first. Returns whether any slots
+    /// were released.
+    pub fn shrink_to_fit_ascending(&mut self) -> bool {
+        let trimmed = self.shrink_to_fit_safe();
+        self.rebuild_free_list_ascending();
+        trimmed
+    }
+
+    /// Rebuild the free list so that empty slots are reused in ascending
+    /// order, lowest slot first, instead of the most recently freed slot
+    /// first. Generations of empty slots are preserved.
+    ///
+    /// Useful for making the slots picked by future insertions deterministic
+    /// and packed toward the front of the arena.
+    pub fn rebuild_free_list_ascending(&mut self) {
+        let slots: Vec<u32> = self
+            .storage
+            .iter()
+            .enumerate()
+            .filter(|(_, entry)| entry.get_empty().is_some())
+            .map(|(slot, _)| slot as u32)
+            .collect();
+
+        self.relink_free_list(&slots);
+    }
+
+    /// Panic because `index` is not contained in an arena with `slots` slots,
+    /// describing what its slot holds instead.
+    #[cold]
+    fn missing_entry(index: Index, entry: Option<&Entry<T>>, slots: usize) -> ! {
+        let slot = match entry {
+            Some(Entry::Occupied(occupied)) => format!(
+                "slot is occupied with generation {}",
+                occupied.generation.to_u32()
+            ),
+            Some(Entry::Placeholder(generation)) => format!(
+                "slot is reserved by a placeholder with generation {}",
+                generation.to_u32()
+            ),
+            Some(Entry::Empty(empty)) => format!(
+                "slot is empty, last occupied with generation {}",
+                empty.generation.to_u32()
+            ),
+            None => format!("slot is out of range, arena has {} slots", slots),
+        };
+
+        panic!("No entry at index {:?}: {}", index, slot)
+    }
+
+    /// In debug builds, panic if this arena has provenance checks enabled and
+    /// the given index wasn't issued by it.
+    pub(crate) fn check_provenance(&self, index: Index) {
+        check_index_provenance(self.provenance, index);
+    }
+
+    /// If auto trimming is enabled and `slot` was the last slot in storage,
+    /// release the empty entries at the end of storage.
+    fn auto_trim_after_remove(&mut self, slot: u32) {
+        if self.auto_trim && slot as usize == self.storage.len().saturating_sub(1) {
+            self.trim_trailing_empty();
+        }
+    }
+
+    /// Reset the side tables for `slot`, which was just given a new value
+    /// with the given tag.
+    fn init_slot_data(&mut self, slot: u32, tag: u16) {
+        self.tags.set(slot, tag);
+        self.versions.set(slot, 0);
+        self.padding.set(slot, false);
+
+        let entity_id = allocate_entity_id(&mut self.next_entity_id);
+        self.entity_ids.set(slot, entity_id);
+    }
+
+    /// Move the side table values for an entry moved from `from` to `to`.
+    fn move_slot_data(&mut self, from: u32, to: u32) {
+        self.tags.move_slot(from, to);
+        self.versions.move_slot(from, to);
+        self.entity_ids.move_slot(from, to);
+        self.padding.move_slot(from, to);
+    }
+
+    /// Copy the side table values for `slot` from `other`, for a value taken
+    /// from it. The value is given a new entity id from this arena, so that
+    /// entity ids stay unique.
+    fn copy_slot_data(&mut self, other: &Self, slot: u32) {
+        self.tags.set(slot, other.tags.get(slot));
+        self.versions.set(slot, other.versions.get(slot));
+        self.padding.set(slot, false);
+
+        let entity_id = allocate_entity_id(&mut self.next_entity_id);
+        self.entity_ids.set(slot, entity_id);
+    }
+
+    /// Shorten storage to `len` slots, forgetting the side table values of the
+    /// slots that were dropped.
+    fn truncate_storage(&mut self, len: usize) {
+        self.storage.truncate(len);
+        self.tags.truncate(len);
+        self.versions.truncate(len);
+        self.entity_ids.truncate(len);
+        self.padding.truncate(len);
+    }
+
+    /// Shrink the capacity of storage and the side tables to fit.
+    fn shrink_storage(&mut self) {
+        self.storage.shrink_to_fit();
+        self.tags.shrink_to_fit();
+        self.versions.shrink_to_fit();
+        self.entity_ids.shrink_to_fit();
+        self.padding.shrink_to_fit();
+    }
+
+    /// Truncate any empty entries off the end of storage, unlinking them from
+    /// the free list. Returns whether any entries were removed.
+    fn trim_trailing_empty(&mut self) -> bool {
+        let new_len = self
+            .storage
+            .iter()
+            .rposition(|entry| entry.get_empty().is_none())
+            .map_or(0, |last| {
+                last.checked_add(1).unwrap_or_else(|| unreachable!())
+            });
+
+        if new_len == self.storage.len() {
+            return false;
+        }
+
+        let remaining: Vec<u32> = self
+            .free_list_snapshot()
+            .into_iter()
+            .filter(|&slot| (slot as usize) < new_len)
+            .collect();
+
+        self.truncate_storage(new_len);
+        self.relink_free_list(&remaining);
+
+        true
+    }
+
+    /// Returns how many times each slot in the arena's storage has been
+    /// reused, measured by how far its generation has advanced from the first
+    /// generation. Invalidating an index also counts as a reuse.
+    ///
+    /// Useful for finding which slots churn the most.
+    pub fn reuse_histogram(&self) -> Vec<u32> {
+        let first = first_generation(self.provenance).to_u32();
+
+        self.storage
+            .iter()
+            .map(|entry| {
+                let generation = match entry {
+                    Entry::Occupied(occupied) => occupied.generation,
+                    Entry::Placeholder(generation) => *generation,
+                    Entry::Empty(empty) => empty.generation,
+                };
+
+                // Generations only go below the first generation by wrapping
+                // around, which loses count of the reuses anyway.
+                generation.to_u32().saturating_sub(first)
+            })
+            .collect()
+    }
+
+    /// Break the arena down into plain parts that can be handed to code that
+    /// understands their layout, and later turned back into an arena with
+    /// [`Arena::from_raw_parts`].
+    ///
+    /// Only values, generations, tags, the free list, and the arena's
+    /// provenance id are kept. Versions, entity ids, and settings like auto
+    /// trimming are dropped.
+    pub fn into_raw_parts(self) -> RawArena<T> {
+        let tags = self.tags;
+        let slots = (0u32..)
+            .zip(self.storage)
+            .map(|(slot, entry)| match entry {
+                Entry::Occupied(occupied) => RawSlot::Occupied {
+                    generation: occupied.generation.to_u32(),
+                    tag: tags.get(slot),
+                    value: occupied.value,
+                },
+                Entry::Placeholder(generation) => RawSlot::Placeholder {
+                    generation: generation.to_u32(),
+                },
+                Entry::Empty(empty) => RawSlot::Empty {
+                    generation: empty.generation.to_u32(),
+                    next_free: empty.next_free.map(FreePointer::slot),
+                },
+            })
+            .collect();
+
+        RawArena {
+            slots,
+            len: self.len,
+            first_free: self.first_free.map(FreePointer::slot),
+            provenance: self.provenance,
+        }
+    }
+
+    /// Rebuild an arena from parts produced by [`Arena::into_raw_parts`],
+    /// trusting that they're consistent. [`Arena::validate`] can check the
+    /// result afterwards.
+    ///
+    /// # Safety
+    ///
+    /// Every generation in `raw` must be nonzero.
+    ///
+    /// The free list and length should also match the slots. If they don't,
+    /// the arena may panic or misbehave, but won't cause undefined behavior.
+    pub unsafe fn from_raw_parts(raw: RawArena<T>) -> Self {
+        let mut tags = SideTable::default();
+        let storage = (0u32..)
+            .zip(raw.slots)
+            .map(|(slot, raw_slot)| match raw_slot {
+                RawSlot::Occupied {
+                    generation,
+                    tag,
+                    value,
+                } => {
+                    tags.set(slot, tag);
+
+                    Entry::Occupied(OccupiedEntry {
+                        generation: Generation::from_u32_unchecked(generation),
+                        value,
+                    })
+                }
+                RawSlot::Placeholder { generation } => {
+                    Entry::Placeholder(Generation::from_u32_unchecked(generation))
+                }
+                RawSlot::Empty {
+                    generation,
+                    next_free,
+                } => Entry::Empty(EmptyEntry {
+                    generation: Generation::from_u32_unchecked(generation),
+                    next_free: next_free.map(FreePointer::from_slot),
+                }),
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            placeholders: count_placeholders(&storage),
+            storage,
+            len: raw.len,
+            peak_len: raw.len,
+            first_free: raw.first_free.map(FreePointer::from_slot),
+            provenance: raw.provenance,
+            tags,
+            ..Self::new()
+        }
+    }
+
+    /// Capture the arena's bookkeeping: which slots are occupied, the
+    /// generation of every slot, and the exact order of the free list. Values
+    /// are not included.
+    pub fn export_state(&self) -> ArenaState {
+        let (generations, occupied) = self
+            .storage
+            .iter()
+            .map(|entry| match entry {
+                Entry::Occupied(occupied) => (occupied.generation.to_u32(), true),
+                Entry::Placeholder(generation) => (generation.to_u32(), true),
+                Entry::Empty(empty) => (empty.generation.to_u32(), false),
+            })
+            .unzip();
+
+        ArenaState {
+            generations,
+            occupied,
+            free_list: self.free_list_snapshot(),
+            len: self.len,
+        }
+    }
+
+    /// Compute a hash of the arena's bookkeeping: which slots are occupied,
+    /// the generation of every slot, and the order of the free list. Values
+    /// are ignored.
+    ///
+    /// Arenas with the same fingerprint will almost certainly hand out the
+    /// same indices for the same sequence of operations. The hash doesn't
+    /// depend on the platform or the build, so fingerprints can be compared
+    /// across machines.
+    pub fn structural_fingerprint(&self) -> u64 {
+        let state = self.export_state();
+        let mut hash = Fnv1a::new();
+
+        hash.write_u64(state.generations.len() as u64);
+        for (&generation, &occupied) in state.generations.iter().zip(&state.occupied) {
+            hash.write_u32(generation);
+            hash.write_u32(u32::from(occupied));
+        }
+
+        hash.write_u64(state.free_list.len() as u64);
+        for &slot in &state.free_list {
+            hash.write_u32(slot);
+        }
+
+        hash.finish()
+    }
+
+    /// Adopt bookkeeping captured with [`Arena::export_state`], so that this
+    /// arena will hand out the same indices as the exported arena for the same
+    /// sequence of operations.
+    ///
+    /// The arena's values stay where they are, so the state must mark exactly
+    /// the arena's occupied slots as occupied. Slots past the end of the
+    /// arena's storage must be empty in the state, and are added. The arena is
+    /// left unchanged if an error is returned.
+    pub fn import_state(&mut self, state: ArenaState) -> Result<(), ImportStateError> {
+        let slots = state.generations.len();
+        if state.occupied.len() != slots {
+            return Err(ImportStateError::Inconsistent);
+        }
+
+        if state.len != self.len {
+            return Err(ImportStateError::Inconsistent);
+        }
+
+        let mut generations = Vec::with_capacity(slots);
+        for (slot, &generation) in (0u32..).zip(state.generations.iter()) {
+            match Generation::try_from_u32(generation) {
+                Some(generation) => generations.push(generation),
+                None => return Err(ImportStateError::InvalidGeneration { slot }),
+            }
+        }
+
+        let longest = slots.max(self.storage.len());
+        for slot in 0..longest {
+            let live = matches!(self.storage.get(slot), Some(entry) if entry.get_empty().is_none());
+            let occupied = state.occupied.get(slot).copied().unwrap_or(false);
+
+            if live != occupied {
+                return Err(ImportStateError::OccupancyMismatch { slot: slot as u32 });
+            }
+        }
+
+        let mut listed = vec![false; slots];
+        for &slot in &state.free_list {
+            match listed.get_mut(slot as usize) {
+                Some(seen) if !*seen && !state.occupied[slot as usize] => *seen = true,
+                _ => return Err(ImportStateError::InvalidFreeList),
+            }
+        }
+        let empty_count = state.occupied.iter().filter(|&&occupied| !occupied).count();
+        if state.free_list.len() != empty_count {
+            return Err(ImportStateError::InvalidFreeList);
+        }
+
+        // Every check has passed, so it's now safe to modify the arena.
+        self.truncate_storage(slots);
+        for (slot, generation) in generations.into_iter().enumerate() {
+            match self.storage.get_mut(slot) {
+                Some(Entry::Occupied(occupied)) => occupied.generation = generation,
+                Some(Entry::Placeholder(placeholder)) => *placeholder = generation,
+                Some(Entry::Empty(empty)) => empty.generation = generation,
+                None => self.storage.push(Entry::Empty(EmptyEntry {
+                    generation,
+                    next_free: None,
+                })),
+            }
+        }
+        self.relink_free_list(&state.free_list);
+        self.epoch = self.epoch.wrapping_add(1);
+
+        Ok(())
+    }
+
+    /// Iterate over all of the indices and values contained in the arena in
+    /// slot order, checking each free list link passed along the way. If a bad
+    /// link is found, an error describing it is yielded and iteration stops.
+    ///
+    /// Useful for arenas built from untrusted data, to find where any
+    /// corruption lies. See also [`Arena::validate`].
+    pub fn iter_checked(&self) -> impl Iterator<Item = Result<(Index, &T), CorruptionError>> {
+        let storage = &self.storage;
+        let mut failed = false;
+
+        (0u32..)
+            .zip(storage.iter())
+            .filter_map(move |(slot, entry)| {
+                if failed {
+                    return None;
+                }
+
+                match entry {
+                    Entry::Occupied(occupied) => Some(Ok((
+                        Index {
+                            slot,
+                            generation: occupied.generation,
+                        },
+                        &occupied.value,
+                    ))),
+                    Entry::Placeholder(_) => None,
+                    Entry::Empty(empty) => {
+                        match check_free_pointer(storage, empty.next_free, Some(slot)) {
+                            Ok(()) => None,
+                            Err(error) => {
+                                failed = true;
+                                Some(Err(error))
+                            }
+                        }
+                    }
+                }
+            })
+    }
+
+    /// Check the arena's bookkeeping for consistency: every free list link
+    /// must point to an empty slot, the free list must reach every empty slot
+    /// exactly once, and the arena's length must match the number of values
+    /// it holds.
+    pub fn validate(&self) -> Result<(), CorruptionError> {
+        let mut occupied = 0u32;
+        for item in self.iter_checked() {
+            item?;
+            occupied = occupied.checked_add(1).unwrap_or_else(|| unreachable!());
+        }
+
+        if occupied != self.len {
+            return Err(CorruptionError::LengthMismatch {
+                len: self.len,
+                occupied,
+            });
+        }
+
+        check_free_pointer(&self.storage, self.first_free, None)?;
+
+        // Every link was checked to point at an empty slot, so walking at most
+        // as many steps as there are empty slots and landing on each one once
+        // means the list is complete and has no cycles.
+        let empty = self
+            .storage
+            .iter()
+            .filter(|entry| entry.get_empty().is_some())
+            .count();
+        let mut visited = vec![false; self.storage.len()];
+        let mut steps = 0usize;
+        let mut next = self.first_free;
+
+        while let Some(free_pointer) = next {
+            let slot = free_pointer.slot() as usize;
+            if visited[slot] || steps == empty {
+                return Err(CorruptionError::FreeListMismatch);
+            }
+
+            visited[slot] = true;
+            steps = steps.checked_add(1).unwrap_or_else(|| unreachable!());
+            next = self.storage[slot]
+                .get_empty()
+                .unwrap_or_else(|| unreachable!())
+                .next_free;
+        }
+
+        if steps != empty {
+            return Err(CorruptionError::FreeListMismatch);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the slot that the next insertion will reuse, if there are any
+    /// empty slots in the arena.
+    pub fn first_free_slot(&self) -> Option<u32> {
+        self.first_free.map(FreePointer::slot)
+    }
+
+    /// Returns the empty slots in the arena in the order they will be reused
+    /// by future insertions. Slots are reused most recently freed first.
+    ///
+    /// Useful for tools that want to independently validate an arena's free
+    /// list.
+    pub fn free_list_snapshot(&self) -> Vec<u32> {
+        let mut slots = Vec::new();
+        let mut next = self.first_free;
+
+        while let Some(free_pointer) = next {
+            let slot = free_pointer.slot();
+            let empty = self
+                .storage
+                .get(slot as usize)
+                .and_then(Entry::get_empty)
+                .unwrap_or_else(|| unreachable!("free list pointed to a non-empty entry"));
+
+            slots.push(slot);
+            next = empty.next_free;
+        }
+
+        slots
+    }
+
+    /// Rebuild the free list so that the given empty slots are reused in
+    /// order. Every empty entry in storage must appear in `slots` exactly once.
+    fn relink_free_list(&mut self, slots: &[u32]) {
+        let mut next_free = None;
+
+        for &slot in slots.iter().rev() {
+            match &mut self.storage[slot as usize] {
+                Entry::Empty(empty) => empty.next_free = next_free,
+                _ => unreachable!("tried to link a non-empty entry into the free list"),
+            }
+
+            next_free = Some(FreePointer::from_slot(slot));
+        }
+
+        self.first_free = next_free;
+    }
+
+    /// Iterate over the indices of every occupied entry and placeholder.
+    fn live_indices(&self) -> impl Iterator<Item = Index> + '_ {
+        self.storage.iter().enumerate().filter_map(|(slot, entry)| {
+            entry.live_generation().map(|generation| Index {
+                slot: slot as u32,
+                generation,
+            })
+        })
+    }
+
+    /// Move every occupied entry and placeholder to the front of the arena,
+    /// truncating storage to fit them exactly and clearing the free list.
+    /// `on_move` is invoked with the old and new index of each entry that was
+    /// relocated.
+    fn defragment_inner<F: FnMut(Index, Index)>(&mut self, mut on_move: F) {
+        let live = self
+            .storage
+            .iter()
+            .filter(|entry| entry.get_empty().is_none())
+            .count();
+
+        // All of the entries that need to be moved live past `live`, and we
+        // always take the last one, so we can keep shrinking the search window.
+        let mut back = self.storage.len();
+
+        for front in 0..live {
+            let empty = match self.storage[front].get_empty() {
+                Some(empty) => empty,
+                None => continue,
+            };
+
+            // There are exactly `live` non-empty entries, so if there's an
+            // empty entry before `live` then there must be a non-empty one
+            // after it.
+            let source = (live..back)
+                .rev()
+                .find(|&slot| self.storage[slot].get_empty().is_none())
+                .unwrap_or_else(|| unreachable!("no occupied entry left to defragment"));
+            back = source;
+
+            // The destination slot may have stale indices pointing at it, so
+            // the moved entry takes the generation that slot would have gotten
+            // on its next insertion.
+            let generation = next_generation(self.provenance, empty.generation);
+
+            let old_generation = match replace(&mut self.storage[source], Entry::Empty(empty)) {
+                Entry::Occupied(occupied) => {
+                    self.storage[front] = Entry::Occupied(OccupiedEntry {
+                        generation,
+                        value: occupied.value,
+                    });
+                    self.move_slot_data(source as u32, front as u32);
+                    occupied.generation
+                }
+                Entry::Placeholder(old_generation) => {
+                    self.storage[front] = Entry::Placeholder(generation);
+                    old_generation
+                }
+                Entry::Empty(_) => unreachable!(),
+            };
+
+            let old = Index {
+                slot: source as u32,
+                generation: old_generation,
+            };
+            let new = Index {
+                slot: front as u32,
+                generation,
+            };
+            self.epoch = self.epoch.wrapping_add(1);
+            on_move(old, new);
+        }
+
+        self.truncate_storage(live);
+        self.first_free = None;
+    }
+}
+
+/// Returns the number of placeholders in `storage`.
+fn count_placeholders<T>(storage: &[Entry<T>]) -> u32 {
+    storage
+        .iter()
+        .filter(|entry| matches!(entry, Entry::Placeholder(_)))
+        .count()
+        .try_into()
+        .unwrap_or_else(|_| unreachable!("Arena storage exceeded what can be represented by a u32"))
+}
+
+/// In debug builds, panic if `provenance` is the id of an arena with
+/// provenance checks enabled and the given index wasn't issued by it.
+pub(crate) fn check_index_provenance(provenance: Option<u8>, index: Index) {
+    if let Some(id) = provenance {
+        debug_assert!(
+            index.generation.to_u32().to_be_bytes()[0] == id,
+            "Index {:?} was not issued by this arena",
+            index
+        );
+    }
+}
+
+/// Returns the provenance id of the arena created with provenance checks after
+/// `count` others. Ids cycle from 1 through 255, skipping zero, since it's the
+/// id of generations from arenas without provenance checks.
+fn provenance_id(count: usize) -> u8 {
+    count
+        .checked_rem(255)
+        .and_then(|id| id.checked_add(1))
+        .and_then(|id| id.try_into().ok())
+        .unwrap_or_else(|| unreachable!())
+}
+
+/// Returns the generation given to a newly created slot in an arena with the
+/// given provenance id.
+fn first_generation(provenance: Option<u8>) -> Generation {
+    match provenance {
+        Some(id) => Generation::from_u32(u32::from_be_bytes([id, 0, 0, 1])),
+        None => Generation::first(),
+    }
+}
+
+/// Returns the generation following `generation` in an arena with the given
+/// provenance id, or `None` if advancing would wrap around.
+fn checked_next_generation(provenance: Option<u8>, generation: Generation) -> Option<Generation> {
+    match provenance {
+        // Only the bits below the id count up.
+        Some(_) if generation.to_u32() & TAGGED_GENERATION_MASK == TAGGED_GENERATION_MASK => None,
+        _ => generation.checked_next(),
+    }
+}
+
+/// Returns the generation following `generation` in an arena with the given
+/// provenance id, wrapping around to the first generation.
+fn next_generation(provenance: Option<u8>, generation: Generation) -> Generation {
+    checked_next_generation(provenance, generation).unwrap_or_else(|| first_generation(provenance))
+}
+
+/// Entry kept by [`Arena::top_k_by`], ordered in reverse according to the
+/// caller's comparison so that a max-heap of them keeps the least at the top.
+struct TopKEntry<'a, 'c, T, F> {
+    index: Index,
+    value: &'a T,
+    cmp: &'c RefCell<F>,
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> Ord for TopKEntry<'_, '_, T, F> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.cmp.borrow_mut())(other.value, self.value)
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> PartialOrd for TopKEntry<'_, '_, T, F> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> PartialEq for TopKEntry<'_, '_, T, F> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<T, F: FnMut(&T, &T) -> Ordering> Eq for TopKEntry<'_, '_, T, F> {}
+
+/// 64-bit FNV-1a hasher, used where hashes need to be stable across platforms
+/// and builds.
+struct Fnv1a(u64);
+
+impl Fnv1a {
+    fn new() -> Self {
+        Fnv1a(0xcbf2_9ce4_8422_2325)
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn write_u32(&mut self, value: u32) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Check that a free list link held by `slot` (or the head of the free list, if
+/// `slot` is `None`) points to an empty entry within `storage`.
+fn check_free_pointer<T>(
+    storage: &[Entry<T>],
+    pointer: Option<FreePointer>,
+    slot: Option<u32>,
+) -> Result<(), CorruptionError> {
+    let pointer = match pointer {
+        Some(pointer) => pointer,
+        None => return Ok(()),
+    };
+
+    match storage.get(pointer.slot() as usize) {
+        Some(Entry::Empty(_)) => Ok(()),
+        Some(_) => Err(CorruptionError::FreePointerToLiveEntry { slot }),
+        None => Err(CorruptionError::FreePointerOutOfRange { slot }),
+    }
+}
+
+/// Hand out the next entity id if entity ids are enabled, advancing the
+/// counter.
+fn allocate_entity_id(next: &mut Option<NonZeroU64>) -> Option<NonZeroU64> {
+    let id = (*next)?;
+    let following = id
+        .get()
+        .checked_add(1)
+        .unwrap_or_else(|| panic!("Arena ran out of entity ids"));
+    *next = NonZeroU64::new(following);
+
+    Some(id)
+}
+
+/// Iterate over the occupied entries of a piece of an arena's storage, where
+/// the first entry is at slot `offset`.
+fn occupied_entries<T>(entries: &[Entry<T>], offset: usize) -> impl Iterator<Item = (Index, &T)> {
+    (offset..)
+        .zip(entries.iter())
+        .filter_map(|(slot, entry)| match entry {
+            Entry::Occupied(occupied) => {
+                let index = Index {
+                    slot: slot as u32,
+                    generation: occupied.generation,
+                };
+                Some((index, &occupied.value))
+            }
+            _ => None,
+        })
+}
+
+/// Iterate over the occupied entries of a piece of an arena's storage with
+/// mutable access, where the first entry is at slot `offset`.
+fn occupied_entries_mut<T>(
+    entries: &mut [Entry<T>],
+    offset: usize,
+) -> impl Iterator<Item = (Index, &mut T)> {
+    (offset..)
+        .zip(entries.iter_mut())
+        .filter_map(|(slot, entry)| match entry {
+            Entry::Occupied(occupied) => {
+                let index = Index {
+                    slot: slot as u32,
+                    generation: occupied.generation,
+                };
+                Some((index, &mut occupied.value))
+            }
+            _ => None,
+        })
+}
+
+impl<T> Default for Arena<T> {
+    fn default() -> Self {
+        Arena::new()
+    }
+}
+
+impl<T> IntoIterator for Arena<T> {
+    type Item = (Index, T);
+    type IntoIter = IntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        IntoIter {
+            arena: self,
+            slot: 0,
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a Arena<T> {
+    type Item = (Index, &'a T);
+    type IntoIter = Iter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut Arena<T> {
+    type Item = (Index, &'a mut T);
+    type IntoIter = IterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> ops::Index<Index> for Arena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        match self.get(index) {
+            Some(value) => value,
+            None => Self::missing_entry(
+                index,
+                self.storage.get(index.slot as usize),
+                self.storage.len(),
+            ),
+        }
+    }
+}
+
+impl<T> ops::IndexMut<Index> for Arena<T> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        self.check_provenance(index);
+        let slots = self.storage.len();
+
+        // Binding the value in a guarded pattern would keep the entry borrowed
+        // in the panicking arm, so the guard only inspects the entry.
+        match self.storage.get_mut(index.slot as usize) {
+            Some(entry) if entry.live_generation() == Some(index.generation) => match entry {
+                Entry::Occupied(occupied) => &mut occupied.value,
+                Entry::Placeholder(_) | Entry::Empty(_) => {
+                    Self::missing_entry(index, Some(entry), slots)
+                }
+            },
+            entry => Self::missing_entry(index, entry.map(|entry| &*entry), slots),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{provenance_id, Arena, Cull, Entry, Index, OccupiedEntry, ResolvedRef};
+    use crate::error::{
+        CorruptionError, Get2Error, ImportStateError, InvalidateError, MergeError, VersionError,
+    };
+    use crate::free_pointer::FreePointer;
+    use crate::generation::Generation;
+
+    use std::mem::size_of;
+
+    #[test]
+    fn arena_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Arena<u32>>();
+    }
+
+    #[test]
+    fn size_of_index() {
+        assert_eq!(size_of::<Index>(), 8);
+        assert_eq!(size_of::<Option<Index>>(), 8);
+    }
+
+    #[test]
+    fn size_of_entry() {
+        // Tags, versions and entity ids live in side tables, so an occupied
+        // entry is only a generation and a value.
+        assert_eq!(
+            size_of::<OccupiedEntry<u32>>(),
+            size_of::<Generation>() + size_of::<u32>()
+        );
+        assert_eq!(size_of::<OccupiedEntry<u64>>(), 16);
+        assert_eq!(size_of::<Entry<u32>>(), 12);
+    }
+
+    #[test]
+    fn new() {
+        let arena: Arena<u32> = Arena::new();
+        assert_eq!(arena.len(), 0);
+        assert_eq!(arena.capacity(), 0);
+    }
+
+    #[test]
+    fn with_capacity() {
+        let arena: Arena<u32> = Arena::with_capacity(8);
+        assert_eq!(arena.len(), 0);
+        assert_eq!(arena.capacity(), 8);
+    }
+
+    #[test]
+    fn insert_and_get() {
+        let mut arena = Arena::new();
+
+        let one = arena.insert(1);
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.get(one), Some(&1));
+
+        let two = arena.insert(2);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(one), Some(&1));
+        assert_eq!(arena.get(two), Some(&2));
+    }
+
+    #[test]
+    fn peak_len() {
+        let mut arena = Arena::new();
+        assert_eq!(arena.peak_len(), 0);
+
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+        for &index in &indices[..4] {
+            arena.remove(index);
+        }
+        arena.insert(5);
+
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.peak_len(), 5);
+    }
+
+    #[test]
+    fn insert_remove_get() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+
+        let two = arena.insert(2);
+        assert_eq!(arena.len(), 2);
+        assert!(arena.contains(two));
+        assert_eq!(arena.remove(two), Some(2));
+        assert!(!arena.contains(two));
+
+        let three = arena.insert(3);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(one), Some(&1));
+        assert_eq!(arena.get(three), Some(&3));
+        assert_eq!(arena.get(two), None);
+    }
+
+    #[test]
+    fn remove_returning_slot() {
+        let mut arena = Arena::new();
+        let _one = arena.insert(1);
+        let two = arena.insert(2);
+
+        assert_eq!(arena.remove_returning_slot(two), Some((two.slot(), 2)));
+        assert_eq!(arena.remove_returning_slot(two), None);
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn contains_all_sorted() {
+        let mut arena = Arena::new();
+        let mut indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        indices.sort();
+
+        let expected = indices.iter().all(|&index| arena.contains(index));
+        assert!(expected);
+        assert_eq!(arena.contains_all_sorted(&indices), expected);
+
+        arena.remove(indices[6]);
+        let expected = indices.iter().all(|&index| arena.contains(index));
+        assert!(!expected);
+        assert_eq!(arena.contains_all_sorted(&indices), expected);
+
+        assert!(arena.contains_all_sorted(&[]));
+
+        // Repeated indices and indices past the end of storage.
+        assert!(arena.contains_all_sorted(&[indices[2], indices[2], indices[9]]));
+        let past_end = Index::from_bits(indices[9].to_bits() + 1);
+        assert!(!arena.contains_all_sorted(&[indices[0], past_end]));
+    }
+
+    #[test]
+    #[should_panic(expected = "indices must be sorted by slot")]
+    fn contains_all_sorted_unsorted() {
+        let mut arena = Arena::new();
+        let first = arena.insert(0);
+        let second = arena.insert(1);
+        arena.contains_all_sorted(&[second, first]);
+    }
+
+    #[test]
+    fn indices_not_in() {
+        let mut arena = Arena::new();
+        let shared = arena.insert("shared");
+        let recycled = arena.insert("recycled");
+        let old = arena.clone();
+
+        arena.remove(recycled);
+        let recycled2 = arena.insert("recycled again");
+        let added = arena.insert("added");
+
+        assert_eq!(arena.indices_not_in(&old), vec![recycled2, added]);
+        assert_eq!(old.indices_not_in(&arena), vec![recycled]);
+        assert!(!arena.indices_not_in(&old).contains(&shared));
+    }
+
+    #[test]
+    fn is_dangling() {
+        let mut arena = Arena::new();
+        let live = arena.insert(1);
+        let removed = arena.insert(2);
+        let recycled = arena.insert(3);
+
+        arena.remove(removed);
+        arena.remove(recycled);
+        let _new = arena.insert(4);
+
+        assert!(!arena.is_dangling(live));
+        assert!(arena.is_dangling(removed));
+        assert!(arena.is_dangling(recycled));
+        assert!(!arena.is_dangling(Index::from_bits(0x00000001_000000FF)));
+    }
+
+    #[test]
+    fn forensics() {
+        let mut arena = Arena::new();
+        let stale = arena.insert("first");
+        arena.remove(stale);
+        let middle = arena.insert("second");
+        arena.remove(middle);
+        let _current = arena.insert("third");
+
+        let report = arena.forensics(stale);
+        assert_eq!(report.current_generation, Some(3));
+        assert_eq!(report.generation_gap, Some(2));
+        assert_eq!(report.occupant, Some(&"third"));
+
+        let empty = arena.insert("empty");
+        arena.remove(empty);
+        let report = arena.forensics(empty);
+        assert_eq!(report.current_generation, Some(1));
+        assert_eq!(report.generation_gap, Some(0));
+        assert_eq!(report.occupant, None);
+
+        let report = arena.forensics(Index::from_bits(0x00000001_000000FF));
+        assert_eq!(report.current_generation, None);
+        assert_eq!(report.occupant, None);
+    }
+
+    #[test]
+    fn insert_remove_get_by_slot() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+
+        let two = arena.insert(2);
+        assert_eq!(arena.len(), 2);
+        assert!(arena.contains(two));
+        assert_eq!(arena.remove_by_slot(two.slot()), Some((two, 2)));
+        assert!(!arena.contains(two));
+        assert_eq!(arena.get_by_slot(two.slot()), None);
+
+        let three = arena.insert(3);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(one), Some(&1));
+        assert_eq!(arena.get(three), Some(&3));
+        assert_eq!(arena.get(two), None);
+        assert_eq!(arena.get_by_slot(two.slot()), Some((three, &3)));
+    }
+
+    #[test]
+    fn insert_and_iter_tagged() {
+        let mut arena = Arena::new();
+
+        let untagged = arena.insert("untagged");
+        let physics = arena.insert_tagged("physics", 1);
+        let audio = arena.insert_tagged("audio", 2);
+        let physics2 = arena.insert_tagged("physics2", 1);
+
+        let tagged: Vec<_> = arena.iter_tagged(1).collect();
+        assert_eq!(tagged, vec![(physics, &"physics"), (physics2, &"physics2")]);
+
+        let tagged: Vec<_> = arena.iter_tagged(2).collect();
+        assert_eq!(tagged, vec![(audio, &"audio")]);
+
+        let tagged: Vec<_> = arena.iter_tagged(0).collect();
+        assert_eq!(tagged, vec![(untagged, &"untagged")]);
+
+        arena.remove(physics);
+        let tagged: Vec<_> = arena.iter_tagged(1).collect();
+        assert_eq!(tagged, vec![(physics2, &"physics2")]);
+    }
+
+    #[test]
+    fn placeholder_lifecycle() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let placeholder = arena.reserve_placeholder();
+
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.get(placeholder), None);
+        assert!(!arena.contains(placeholder));
+        assert_eq!(arena.iter().collect::<Vec<_>>(), vec![(one, &1)]);
+
+        // Placeholders hold onto their slot.
+        let two = arena.insert(2);
+        assert_ne!(two.slot(), placeholder.slot());
+
+        assert_eq!(arena.fulfill(one, 10), Err(10));
+        assert_eq!(arena.fulfill(placeholder, 3), Ok(()));
+        assert_eq!(arena.fulfill(placeholder, 4), Err(4));
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.get(placeholder), Some(&3));
+        assert_eq!(arena.iter().count(), 3);
+    }
+
+    #[test]
+    fn gc_placeholder() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let placeholder = arena.reserve_placeholder();
+        arena.remove(one);
+
+        let remap = arena.gc();
+        assert_eq!(remap.len(), 1);
+        assert_eq!(arena.fulfill(remap[&placeholder], 2), Ok(()));
+        assert_eq!(arena.get(remap[&placeholder]), Some(&2));
+    }
+
+    #[test]
+    fn iter_since_generation() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        let watermark = 2;
+
+        arena.remove(indices[1]);
+        arena.remove(indices[3]);
+        let five = arena.insert(5);
+        let six = arena.insert(6);
+
+        let mut recent: Vec<_> = arena.iter_since_generation(watermark).collect();
+        recent.sort();
+        assert_eq!(recent, vec![(six, &6), (five, &5)]);
+
+        assert_eq!(arena.iter_since_generation(1).count(), 4);
+    }
+
+    #[test]
+    fn iter_bits() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+
+        let entries: Vec<_> = arena
+            .iter_bits()
+            .map(|(bits, &value)| (Index::from_bits(bits), value))
+            .collect();
+        assert_eq!(
+            entries,
+            vec![(indices[0], 0), (indices[2], 2), (indices[3], 3)]
+        );
+    }
+
+    #[test]
+    fn split_at_slot() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+        arena.remove(indices[7]);
+
+        let (front, back) = arena.split_at_slot(5);
+        let front: Vec<_> = front.map(|(_, &value)| value).collect();
+        let back: Vec<_> = back.map(|(_, &value)| value).collect();
+        assert_eq!(front, vec![0, 1, 3, 4]);
+        assert_eq!(back, vec![5, 6, 8, 9]);
+
+        let (front, back) = arena.split_at_slot(100);
+        assert_eq!(front.count(), 8);
+        assert_eq!(back.count(), 0);
+    }
+
+    #[test]
+    fn split_at_slot_mut() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+        arena.remove(indices[7]);
+
+        {
+            let (front, back) = arena.split_at_slot_mut(5);
+            front.for_each(|(_, value)| *value += 100);
+            back.for_each(|(_, value)| *value += 200);
+        }
+
+        let mut seen = 0;
+        for (index, &value) in arena.iter() {
+            if index.slot() < 5 {
+                assert_eq!(value, index.slot() + 100);
+            } else {
+                assert_eq!(value, index.slot() + 200);
+            }
+            seen += 1;
+        }
+        assert_eq!(seen, 8);
+    }
+
+    #[test]
+    fn insert_recycled() {
+        let mut arena: Arena<Vec<u8>> = Arena::new();
+
+        let first = arena.insert_recycled(|buffer| buffer.extend_from_slice(b"hello"));
+        assert_eq!(arena[first], b"hello");
+        let pointer = arena[first].as_ptr();
+
+        assert!(arena.remove_recycling(first));
+        assert!(!arena.remove_recycling(first));
+
+        let second = arena.insert_recycled(|buffer| {
+            buffer.clear();
+            buffer.extend_from_slice(b"world");
+        });
+        assert_eq!(arena[second], b"world");
+        assert_eq!(arena[second].as_ptr(), pointer);
+    }
+
+    #[test]
+    fn lookup_by_entry_state() {
+        let mut arena = Arena::new();
+        let live = arena.insert(1);
+        let stale = arena.insert(2);
+        let empty = arena.insert(3);
+        let placeholder = arena.reserve_placeholder();
+        let out_of_range = Index::from_bits(0x00000001_000000FF);
+
+        arena.remove(stale);
+        let recycled = arena.insert(4);
+        assert_eq!(recycled.slot(), stale.slot());
+        arena.remove(empty);
+
+        assert_eq!(arena.get(live), Some(&1));
+        assert_eq!(arena.get(recycled), Some(&4));
+        assert_eq!(arena.get_mut(live), Some(&mut 1));
+
+        for &index in &[stale, empty, placeholder, out_of_range] {
+            assert_eq!(arena.get(index), None);
+            assert_eq!(arena.get_mut(index), None);
+            assert!(!arena.contains(index));
+        }
+    }
+
+    #[test]
+    fn get_mut() {
+        let mut arena = Arena::new();
+        let foo = arena.insert(5);
+
+        let handle = arena.get_mut(foo).unwrap();
+        *handle = 6;
+
+        assert_eq!(arena.get(foo), Some(&6));
+    }
+
+    #[test]
+    fn get_with_generation() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        arena.remove(foo);
+
+        let bar = arena.insert("bar");
+        assert_eq!(arena.get_with_generation(bar), Some((2, &"bar")));
+        assert_eq!(
+            arena
+                .get_with_generation(bar)
+                .map(|(generation, _)| generation),
+            Some((bar.to_bits() >> 32) as u32)
+        );
+        assert_eq!(arena.get_with_generation(foo), None);
+    }
+
+    #[test]
+    fn replace_if_version() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        assert_eq!(arena.get_versioned(foo), Some((&"foo", 0)));
+
+        assert_eq!(arena.replace_if_version(foo, 0, "bar"), Ok(1));
+        assert_eq!(arena.get_versioned(foo), Some((&"bar", 1)));
+
+        assert_eq!(
+            arena.replace_if_version(foo, 0, "baz"),
+            Err(VersionError::Mismatch { current: 1 })
+        );
+        assert_eq!(arena.get_versioned(foo), Some((&"bar", 1)));
+
+        // Reads don't affect versions.
+        assert_eq!(arena.get(foo), Some(&"bar"));
+        assert_eq!(arena.get_versioned(foo), Some((&"bar", 1)));
+
+        arena.remove(foo);
+        assert_eq!(
+            arena.replace_if_version(foo, 1, "baz"),
+            Err(VersionError::Missing)
+        );
+
+        // A new value in the same slot starts over.
+        let reused = arena.insert("qux");
+        assert_eq!(reused.slot(), foo.slot());
+        assert_eq!(arena.get_versioned(reused), Some((&"qux", 0)));
+
+        // Versions follow values that are moved by compaction.
+        let mut arena = Arena::new();
+        let front = arena.insert(0);
+        let back = arena.insert(1);
+        assert_eq!(arena.replace_if_version(back, 0, 2), Ok(1));
+        arena.remove(front);
+
+        let remap = arena.gc();
+        assert_eq!(remap[&back].slot(), front.slot());
+        assert_eq!(arena.get_versioned(remap[&back]), Some((&2, 1)));
+    }
+
+    #[test]
+    fn get2_mut_detailed() {
+        let mut arena = Arena::new();
+        let foo = arena.insert(1);
+        let bar = arena.insert(2);
+        let removed = arena.insert(3);
+        arena.remove(removed);
+
+        {
+            let (foo_value, bar_value) = arena.get2_mut_detailed(foo, bar).unwrap();
+            std::mem::swap(foo_value, bar_value);
+        }
+        assert_eq!(arena[foo], 2);
+        assert_eq!(arena[bar], 1);
+
+        let (bar_value, _) = arena.get2_mut_detailed(bar, foo).unwrap();
+        assert_eq!(*bar_value, 1);
+
+        assert_eq!(arena.get2_mut_detailed(foo, foo), Err(Get2Error::Alias));
+        assert_eq!(
+            arena.get2_mut_detailed(removed, foo),
+            Err(Get2Error::FirstMissing)
+        );
+        assert_eq!(
+            arena.get2_mut_detailed(foo, removed),
+            Err(Get2Error::SecondMissing)
+        );
+        assert_eq!(
+            arena.get2_mut_detailed(removed, removed),
+            Err(Get2Error::BothMissing)
+        );
+    }
+
+    #[test]
+    fn get_mut_and_refs() {
+        let mut arena = Arena::new();
+        let prev = arena.insert(1.0);
+        let edge = arena.insert(0.0);
+        let next = arena.insert(3.0);
+        let removed = arena.insert(100.0);
+        arena.remove(removed);
+
+        let (value, [prev_value, next_value, removed_value]) =
+            arena.get_mut_and_refs(edge, [prev, next, removed]).unwrap();
+        assert_eq!(removed_value, None);
+        *value = (prev_value.unwrap() + next_value.unwrap()) / 2.0;
+
+        assert_eq!(arena[edge], 2.0);
+        assert!(arena.get_mut_and_refs(removed, [prev]).is_none());
+    }
+
+    #[test]
+    #[should_panic(expected = "aliases the target entry")]
+    fn get_mut_and_refs_alias() {
+        let mut arena = Arena::new();
+        let edge = arena.insert(0);
+        let other = arena.insert(1);
+
+        arena.get_mut_and_refs(edge, [other, edge]);
+    }
+
+    #[test]
+    fn get_cloned() {
+        let mut arena = Arena::new();
+        let foo = arena.insert(vec![1, 2]);
+
+        let mut cloned = arena.get_cloned(foo).unwrap();
+        assert_eq!(cloned, vec![1, 2]);
+
+        cloned.push(3);
+        assert_eq!(arena[foo], vec![1, 2]);
+
+        arena.remove(foo);
+        assert_eq!(arena.get_cloned(foo), None);
+    }
+
+    #[test]
+    fn insert_remove_insert_capacity() {
+        let mut arena = Arena::with_capacity(2);
+        assert_eq!(arena.capacity(), 2);
+
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.capacity(), 2);
+
+        arena.remove(a);
+        arena.remove(b);
+        assert_eq!(arena.len(), 0);
+        assert_eq!(arena.capacity(), 2);
+
+        let _a2 = arena.insert("a2");
+        let _b2 = arena.insert("b2");
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.capacity(), 2);
+    }
+
+    #[test]
+    fn invalidate() {
+        let mut arena = Arena::new();
+
+        let a = arena.insert("a");
+        assert_eq!(arena.get(a), Some(&"a"));
+
+        let new_a = arena.invalidate(a).unwrap();
+        assert_eq!(arena.get(a), None);
+        assert_eq!(arena.get(new_a), Some(&"a"));
+    }
+
+    #[test]
+    fn generation_warning() {
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let mut arena = Arena::new();
+
+        let sink = Arc::clone(&warnings);
+        arena.set_generation_warning(
+            4,
+            Box::new(move |slot, generation| sink.lock().unwrap().push((slot, generation))),
+        );
+
+        let _zero = arena.insert(0);
+        let mut one = arena.insert(1);
+        for _ in 0..5 {
+            arena.remove(one);
+            one = arena.insert(1);
+        }
+        one = arena.invalidate(one).unwrap();
+        arena.remove(one);
+
+        assert_eq!(*warnings.lock().unwrap(), vec![(1, 4)]);
+    }
+
+    #[test]
+    fn fold_mut() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| arena.insert((i * 10, 0))).collect();
+        arena.remove(indices[2]);
+
+        let (sum, count) = arena.fold_mut((0, 0), |(sum, ordinal), _, value| {
+            value.1 = ordinal;
+            (sum + value.0, ordinal + 1)
+        });
+
+        assert_eq!(sum, 80);
+        assert_eq!(count, 4);
+        assert_eq!(arena[indices[0]], (0, 0));
+        assert_eq!(arena[indices[1]], (10, 1));
+        assert_eq!(arena[indices[3]], (30, 2));
+        assert_eq!(arena[indices[4]], (40, 3));
+    }
+
+    #[test]
+    fn generation_exhausted() {
+        use std::sync::{Arc, Mutex};
+
+        let exhausted = Arc::new(Mutex::new(Vec::new()));
+        let mut arena = Arena::new();
+
+        let sink = Arc::clone(&exhausted);
+        arena.on_generation_exhausted(move |slot| sink.lock().unwrap().push(slot));
+        arena.set_generation_exhaustion_threshold(3);
+
+        let _zero = arena.insert(0);
+        let mut one = arena.insert(1);
+        for _ in 0..3 {
+            arena.remove(one);
+            one = arena.insert(1);
+        }
+        assert_eq!(*exhausted.lock().unwrap(), vec![1]);
+
+        arena.invalidate(one);
+        assert_eq!(*exhausted.lock().unwrap(), vec![1, 1]);
+    }
+
+    #[test]
+    fn generation_hooks_report_old_generation() {
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let exhausted = Arc::new(Mutex::new(Vec::new()));
+        let mut arena = Arena::new();
+
+        let sink = Arc::clone(&warnings);
+        arena.set_generation_warning(
+            1,
+            Box::new(move |slot, generation| sink.lock().unwrap().push((slot, generation))),
+        );
+        let sink = Arc::clone(&exhausted);
+        arena.on_generation_exhausted(move |slot| sink.lock().unwrap().push(slot));
+        arena.set_generation_exhaustion_threshold(2);
+
+        let removed = arena.insert(0);
+        let invalidated = arena.insert(1);
+        let try_invalidated = arena.insert(2);
+
+        // Every path reports the generation of the index it was given, which
+        // is still below the exhaustion threshold.
+        arena.remove(removed);
+        let invalidated = arena.invalidate(invalidated).unwrap();
+        arena.try_invalidate(try_invalidated).unwrap();
+        assert_eq!(*warnings.lock().unwrap(), vec![(0, 1), (1, 1), (2, 1)]);
+        assert!(exhausted.lock().unwrap().is_empty());
+
+        arena.invalidate(invalidated);
+        assert_eq!(*exhausted.lock().unwrap(), vec![1]);
+    }
+
+    #[test]
+    fn clone_drops_generation_hooks() {
+        use std::sync::{Arc, Mutex};
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let mut arena = Arena::new();
+
+        let sink = Arc::clone(&calls);
+        arena.set_generation_warning(
+            1,
+            Box::new(move |slot, _| sink.lock().unwrap().push(("warning", slot))),
+        );
+        let sink = Arc::clone(&calls);
+        arena.on_generation_exhausted(move |slot| sink.lock().unwrap().push(("exhausted", slot)));
+        arena.set_generation_exhaustion_threshold(1);
+
+        let foo = arena.insert("foo");
+        let mut clone = arena.clone();
+        clone.remove(foo);
+        assert!(calls.lock().unwrap().is_empty());
+
+        arena.remove(foo);
+        assert_eq!(
+            *calls.lock().unwrap(),
+            vec![("warning", 0), ("exhausted", 0)]
+        );
+    }
+
+    #[test]
+    fn replace_where() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        let replaced = arena.replace_where(|&value| value % 3 == 0, |_, &old| old + 100);
+        assert_eq!(replaced, 2);
+
+        let values: Vec<_> = indices.iter().map(|&index| arena[index]).collect();
+        assert_eq!(values, vec![100, 1, 2, 103, 4, 5]);
+    }
+
+    #[test]
+    fn try_invalidate() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+
+        let new_a = arena.try_invalidate(a).unwrap();
+        assert_eq!(arena.get(new_a), Some(&"a"));
+        assert_eq!(arena.try_invalidate(a), Err(InvalidateError::Missing));
+
+        match &mut arena.storage[new_a.slot as usize] {
+            Entry::Occupied(occupied) => occupied.generation = Generation::from_u32(u32::MAX),
+            _ => unreachable!(),
+        }
+        let max_a = arena.contains_slot(new_a.slot()).unwrap();
+
+        assert_eq!(
+            arena.try_invalidate(max_a),
+            Err(InvalidateError::GenerationExhausted)
+        );
+        assert_eq!(arena.get(max_a), Some(&"a"));
+    }
+
+    #[test]
+    fn retain() {
+        let mut arena = Arena::new();
+
+        for i in 0..100 {
+            arena.insert(i);
+        }
+
+        arena.retain(|_, &mut i| i % 2 == 1);
+
+        for (_, i) in arena.iter() {
+            assert_eq!(i % 2, 1);
+        }
+
+        assert_eq!(arena.len(), 50);
+    }
+
+    #[test]
+    fn iter_mut_cull() {
+        let mut arena = Arena::new();
+        for health in &[10, 0, 5, 0, 0, 20] {
+            arena.insert(*health);
+        }
+
+        let culled = arena.iter_mut_cull(|_, health| {
+            if *health == 0 {
+                Cull::Remove
+            } else {
+                *health -= 1;
+                Cull::Keep
+            }
+        });
+
+        assert_eq!(culled, 3);
+        assert_eq!(arena.len(), 3);
+
+        let mut remaining: Vec<_> = arena.iter().map(|(_, &health)| health).collect();
+        remaining.sort();
+        assert_eq!(remaining, vec![4, 9, 19]);
+    }
+
+    #[test]
+    fn remove_where_indices() {
+        use std::rc::Rc;
+
+        let marker = Rc::new(());
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6)
+            .map(|i| arena.insert((i, Rc::clone(&marker))))
+            .collect();
+
+        let removed = arena.remove_where_indices(|_, (i, _)| i % 2 == 0);
+        assert_eq!(removed, vec![indices[0], indices[2], indices[4]]);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(Rc::strong_count(&marker), 4);
+
+        for &index in &removed {
+            assert!(!arena.contains(index));
+        }
+    }
+
+    #[test]
+    fn gc() {
+        let mut arena = Arena::with_capacity(100);
+        let indices: Vec<_> = (0..100).map(|i| arena.insert(i)).collect();
+
+        for (i, &index) in indices.iter().enumerate() {
+            if i % 3 != 0 {
+                arena.remove(index);
+            }
+        }
+
+        let remap = arena.gc();
+        assert_eq!(arena.len(), 34);
+        assert!(arena.capacity() < 100);
+        assert_eq!(remap.len(), 34);
+
+        for slot in 0..34 {
+            assert!(arena.contains_slot(slot).is_some());
+        }
+
+        for (i, &index) in indices.iter().enumerate() {
+            if i % 3 == 0 {
+                assert_eq!(arena.get(remap[&index]), Some(&i));
+            } else {
+                assert!(!remap.contains_key(&index));
+            }
+        }
+    }
+
+    #[test]
+    fn defragment_with() {
+        let mut arena = Arena::with_capacity(8);
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+        for &i in &[0, 2, 3] {
+            arena.remove(indices[i]);
+        }
+
+        let mut moves = Vec::new();
+        arena.defragment_with(|old, new| moves.push((old, new)));
+        moves.sort();
+
+        assert_eq!(moves.len(), 3);
+        let moved_from: Vec<_> = moves.iter().map(|(old, _)| *old).collect();
+        assert_eq!(moved_from, vec![indices[5], indices[6], indices[7]]);
+
+        for &(old, new) in &moves {
+            assert!(new.slot() < 5);
+            assert_eq!(arena.get(old), None);
+            assert_eq!(arena.get(new), Some(&(old.slot())));
+        }
+        for &i in &[1, 4] {
+            assert_eq!(arena.get(indices[i]), Some(&(i as u32)));
+        }
+
+        assert_eq!(arena.capacity(), 8);
+    }
+
+    #[test]
+    fn compact_and_shrink() {
+        let mut arena = Arena::new();
+        let mut indices: Vec<_> = (0..50).map(|i| arena.insert(i)).collect();
+        let old_indices = indices.clone();
+
+        for i in (0..50).filter(|i| i % 5 != 0) {
+            arena.remove(indices[i]);
+        }
+
+        arena.compact_and_shrink(|old, new| {
+            let position = indices.iter().position(|&index| index == old).unwrap();
+            indices[position] = new;
+        });
+
+        assert_eq!(arena.len(), 10);
+        assert_eq!(arena.storage.len(), 10);
+        assert_eq!(arena.capacity(), 10);
+        assert!(arena.first_free.is_none());
+
+        for i in (0..50).filter(|i| i % 5 == 0) {
+            assert_eq!(arena.get(indices[i]), Some(&i));
+
+            if indices[i] != old_indices[i] {
+                assert_eq!(arena.get(old_indices[i]), None);
+            }
+        }
+    }
+
+    #[test]
+    fn shrink_to_fit_safe() {
+        let mut arena = Arena::with_capacity(8);
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        arena.remove(indices[1]);
+        arena.remove(indices[4]);
+        arena.remove(indices[5]);
+
+        assert!(arena.shrink_to_fit_safe());
+        assert_eq!(arena.capacity(), 4);
+        for &i in &[0, 2, 3] {
+            assert_eq!(arena.get(indices[i]), Some(&i));
+        }
+
+        // The remaining hole is still reused before growing storage.
+        let six = arena.insert(6);
+        assert_eq!(six.slot(), 1);
+        let seven = arena.insert(7);
+        assert_eq!(seven.slot(), 4);
+
+        // The last slot is occupied, so there's nothing to trim.
+        arena.remove(indices[2]);
+        assert!(!arena.shrink_to_fit_safe());
+        assert_eq!(arena.len(), 4);
+    }
+
+    #[test]
+    fn rebuild_free_list_ascending() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+
+        for &i in &[5, 1, 6, 3] {
+            arena.remove(indices[i]);
+        }
+        arena.rebuild_free_list_ascending();
+
+        let slots: Vec<_> = (0..5).map(|i| arena.insert(i).slot()).collect();
+        assert_eq!(slots, vec![1, 3, 5, 6, 8]);
+
+        // Generations were preserved, so old indices stay invalid.
+        for &i in &[5, 1, 6, 3] {
+            assert_eq!(arena.get(indices[i]), None);
+        }
+    }
+
+    #[test]
+    fn insert_failure_keeps_len() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+
+        // Pretend the arena is full, which is the one failure `insert` can
+        // observe before touching storage.
+        arena.len = u32::MAX;
+
+        assert_eq!(arena.try_insert(2), Err(2));
+        assert_eq!(arena.len, u32::MAX);
+
+        let result = catch_unwind(AssertUnwindSafe(|| arena.insert(3)));
+        assert!(result.is_err());
+        assert_eq!(arena.len, u32::MAX);
+        assert_eq!(arena.storage.len(), 1);
+
+        arena.len = 1;
+        let two = arena.try_insert(2).unwrap();
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(one), Some(&1));
+        assert_eq!(arena.get(two), Some(&2));
+    }
+
+    #[test]
+    fn insert_reusing() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        for &i in &[1, 3, 5] {
+            arena.remove(indices[i]);
+        }
+        assert_eq!(arena.free_list_snapshot(), vec![5, 3, 1]);
+
+        // Slot 3 sits in the middle of the free list.
+        let three = arena.insert_reusing(3, 30);
+        assert_eq!(three.slot(), 3);
+        assert_ne!(three, indices[3]);
+        assert_eq!(arena.get(three), Some(&30));
+        assert_eq!(arena.get(indices[3]), None);
+        assert_eq!(arena.free_list_snapshot(), vec![5, 1]);
+
+        // Slot 0 is occupied, so the head of the free list is used instead.
+        let five = arena.insert_reusing(0, 50);
+        assert_eq!(five.slot(), 5);
+        assert_eq!(arena.get(indices[0]), Some(&0));
+        assert_eq!(arena.free_list_snapshot(), vec![1]);
+
+        // Out of bounds slots also fall back to a normal insert.
+        let one = arena.insert_reusing(100, 10);
+        assert_eq!(one.slot(), 1);
+        assert_eq!(arena.free_list_snapshot(), Vec::<u32>::new());
+
+        assert_eq!(arena.len(), 6);
+        assert_eq!(arena.insert(6).slot(), 6);
+    }
+
+    #[test]
+    fn with_mut() {
+        let mut arena = Arena::new();
+        let foo = arena.insert(vec![1, 2]);
+
+        let len = arena.with_mut(foo, |value| {
+            value.push(3);
+            value.len()
+        });
+        assert_eq!(len, Some(3));
+        assert_eq!(arena[foo], vec![1, 2, 3]);
+
+        arena.remove(foo);
+        assert_eq!(arena.with_mut(foo, |value| value.len()), None);
+    }
+
+    #[test]
+    fn entity_ids() {
+        let mut arena = Arena::new();
+        let before = arena.insert("before");
+        assert_eq!(arena.entity_id(before), None);
+
+        arena.enable_entity_ids();
+        let foo = arena.insert("foo");
+        let bar = arena.insert("bar");
+        let foo_id = arena.entity_id(foo).unwrap();
+        let bar_id = arena.entity_id(bar).unwrap();
+        assert!(bar_id > foo_id);
+        assert_eq!(arena.find_by_entity_id(foo_id), Some(foo));
+
+        // Reusing foo's slot gives the new value a fresh id.
+        arena.remove(foo);
+        let baz = arena.insert("baz");
+        assert_eq!(baz.slot(), foo.slot());
+        let baz_id = arena.entity_id(baz).unwrap();
+        assert!(baz_id > bar_id);
+
+        assert_eq!(arena.entity_id(foo), None);
+        assert_eq!(arena.find_by_entity_id(foo_id), None);
+        assert_eq!(arena.find_by_entity_id(baz_id), Some(baz));
+
+        // Entity ids follow values that are moved by compaction.
+        arena.remove(before);
+        let remap = arena.gc();
+        assert_eq!(remap[&bar].slot(), before.slot());
+        assert_eq!(arena.entity_id(remap[&bar]), Some(bar_id));
+        assert_eq!(arena.find_by_entity_id(bar_id), Some(remap[&bar]));
+    }
+
+    #[test]
+    fn insert_if_slot_free() {
+        let mut arena = Arena::new();
+        let zero = arena.insert(0);
+
+        assert_eq!(arena.insert_if_slot_free(0, 1), Err(1));
+        assert_eq!(arena.get(zero), Some(&0));
+
+        // Slots past the end are created, with the gap left empty.
+        let three = arena.insert_if_slot_free(3, 3).unwrap();
+        assert_eq!(three.slot(), 3);
+        assert_eq!(arena.get(three), Some(&3));
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.free_list_snapshot(), vec![2, 1]);
+
+        let one = arena.insert_if_slot_free(1, 1).unwrap();
+        assert_eq!(one.slot(), 1);
+        assert_eq!(arena.free_list_snapshot(), vec![2]);
+        assert_eq!(arena.insert_if_slot_free(3, 30), Err(30));
+
+        arena.remove(zero);
+        let zero_again = arena.insert_if_slot_free(0, 10).unwrap();
+        assert_ne!(zero_again, zero);
+        assert_eq!(arena.get(zero), None);
+        assert_eq!(arena.insert(2).slot(), 2);
+        assert_eq!(arena.len(), 4);
+    }
+
+    #[test]
+    fn pack_values() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i * 10)).collect();
+        arena.remove(indices[1]);
+        arena.remove(indices[3]);
+        arena.reserve_placeholder();
+
+        let (values, table) = arena.pack_values();
+        assert_eq!(values, vec![&0, &20, &40]);
+        assert_eq!(table, vec![Some(0), None, Some(1), None, Some(2)]);
+
+        for (index, value) in arena.iter() {
+            let dense = table[index.slot() as usize].unwrap();
+            assert_eq!(values[dense as usize], value);
+        }
+    }
+
+    #[test]
+    fn retain_ordered_by() {
+        let mut arena = Arena::new();
+        let three = arena.insert(3);
+        let one = arena.insert(1);
+        let two = arena.insert(2);
+
+        let mut visited = Vec::new();
+        arena.retain_ordered_by(
+            |&value| value,
+            |index, value| {
+                visited.push((index, *value));
+                *value % 2 == 1
+            },
+        );
+
+        assert_eq!(visited, vec![(one, 1), (two, 2), (three, 3)]);
+        assert_eq!(arena.len(), 2);
+        assert!(!arena.contains(two));
+    }
+
+    #[test]
+    fn remove_reporting_empty() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        let bar = arena.insert("bar");
+
+        assert_eq!(arena.remove_reporting_empty(foo), Some(("foo", false)));
+        assert_eq!(arena.remove_reporting_empty(foo), None);
+        assert_eq!(arena.remove_reporting_empty(bar), Some(("bar", true)));
+    }
+
+    #[test]
+    fn shrink_to_fit_ascending() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+        for &i in &[1, 6, 3, 7, 5] {
+            arena.remove(indices[i]);
+        }
+
+        assert!(arena.shrink_to_fit_ascending());
+        assert_eq!(arena.storage.len(), 5);
+        assert_eq!(arena.free_list_snapshot(), vec![1, 3]);
+
+        assert_eq!(arena.insert(10).slot(), 1);
+        assert_eq!(arena.insert(11).slot(), 3);
+        assert_eq!(arena.insert(12).slot(), 5);
+        assert!(!arena.shrink_to_fit_ascending());
+    }
+
+    #[test]
+    fn get_disjoint_mut() {
+        let mut arena = Arena::new();
+        let stale = arena.insert("stale");
+        arena.remove(stale);
+        let foo = arena.insert("foo");
+        let bar = arena.insert("bar");
+        assert_eq!(foo.slot(), stale.slot());
+
+        let [a, b, c] = arena.get_disjoint_mut([bar, stale, foo]);
+        assert_eq!(b, None);
+        *a.unwrap() = "bar2";
+        *c.unwrap() = "foo2";
+
+        assert_eq!(arena[foo], "foo2");
+        assert_eq!(arena[bar], "bar2");
+    }
+
+    #[test]
+    #[should_panic]
+    fn get_disjoint_mut_alias() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        let bar = arena.insert("bar");
+        arena.get_disjoint_mut([foo, bar, foo]);
+    }
+
+    #[test]
+    fn with_shape_of() {
+        let mut names = Arena::new();
+        let indices: Vec<_> = ["a", "b", "c", "d"]
+            .iter()
+            .map(|&name| names.insert(name))
+            .collect();
+        names.remove(indices[1]);
+        let e = names.insert("e");
+        names.remove(indices[3]);
+
+        let mut sizes = Arena::with_shape_of(&names, |index| names[index].len() * 10);
+        assert_eq!(sizes.len(), names.len());
+
+        for (index, _) in names.iter() {
+            assert_eq!(sizes.get(index), Some(&10));
+        }
+        assert_eq!(sizes.get(indices[1]), None);
+        assert_eq!(sizes.get(indices[3]), None);
+        assert_eq!(sizes.get(e), Some(&10));
+
+        assert_eq!(names.insert("f"), sizes.insert(10));
+    }
+
+    #[test]
+    fn apply_to_all() {
+        struct Node {
+            name: &'static str,
+            parent: Option<Index>,
+        }
+
+        let mut arena = Arena::new();
+        let temp = arena.insert(Node {
+            name: "temp",
+            parent: None,
+        });
+        let root = arena.insert(Node {
+            name: "root",
+            parent: None,
+        });
+        let child = arena.insert(Node {
+            name: "child",
+            parent: Some(root),
+        });
+
+        arena.remove(temp);
+        let remap = arena.gc();
+        arena.apply_to_all(|_, node| {
+            node.parent = node.parent.map(|parent| remap[&parent]);
+        });
+
+        let child = remap[&child];
+        let parent = arena[child].parent.unwrap();
+        assert_eq!(arena[parent].name, "root");
+    }
+
+    #[test]
+    fn auto_trim() {
+        let mut arena = Arena::new();
+        arena.set_auto_trim(true);
+
+        let mut stack: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        while let Some(index) = stack.pop() {
+            arena.remove(index);
+            assert_eq!(arena.storage.len(), arena.len());
+        }
+
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+        arena.remove(indices[4]);
+        assert_eq!(arena.storage.len(), 6);
+
+        // Removing the last slot also releases the hole before it, leaving the
+        // hole at slot 1 in the free list.
+        arena.remove(indices[5]);
+        assert_eq!(arena.storage.len(), 4);
+        assert_eq!(arena.free_list_snapshot(), vec![1]);
+
+        assert_eq!(arena.insert(10).slot(), 1);
+        assert_eq!(arena.insert(11).slot(), 4);
+        assert_eq!(arena.len(), 5);
+    }
+
+    #[test]
+    fn provenance_checks() {
+        let mut arena = Arena::with_provenance_checks();
+        let foo = arena.insert("foo");
+        arena.remove(foo);
+        let bar = arena.insert("bar");
+
+        assert_eq!(arena.get(foo), None);
+        assert_eq!(arena.get(bar), Some(&"bar"));
+        assert_eq!(
+            arena.invalidate(bar).and_then(|bar| arena.get(bar)),
+            Some(&"bar")
+        );
+    }
+
+    #[test]
+    fn provenance_id_wraps() {
+        assert_eq!(provenance_id(0), 1);
+        assert_eq!(provenance_id(254), 255);
+
+        // The 256th arena reuses the first id rather than getting zero, which
+        // would switch its checks off.
+        assert_eq!(provenance_id(255), 1);
+        assert!((0..1024).all(|count| provenance_id(count) != 0));
+
+        let mut arena = Arena {
+            provenance: Some(provenance_id(255)),
+            ..Arena::new()
+        };
+        let foo = arena.insert("foo");
+        assert_eq!(foo.to_bits() >> 56, 1);
+        arena.remove(foo);
+        let bar = arena.insert("bar");
+        assert_eq!(arena.get(foo), None);
+        assert_eq!(arena.get(bar), Some(&"bar"));
+    }
+
+    #[test]
+    fn provenance_checks_bits_with_tag() {
+        let mut arena = Arena::with_provenance_checks();
+        let foo = arena.insert("foo");
+        assert_eq!(foo.try_to_bits_with_tag(3), None);
+
+        let mut plain = Arena::new();
+        let bar = plain.insert("bar");
+        let bits = bar.try_to_bits_with_tag(3).unwrap();
+        assert_eq!(Index::from_bits_with_tag(bits), (3, bar));
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    #[should_panic]
+    fn provenance_checks_wrong_arena() {
+        let mut first = Arena::with_provenance_checks();
+        let mut second = Arena::with_provenance_checks();
+        second.insert("second");
+
+        let foo = first.insert("foo");
+        second.get(foo);
+    }
+
+    #[test]
+    #[cfg(debug_assertions)]
+    fn provenance_checks_every_lookup() {
+        use std::panic::{catch_unwind, AssertUnwindSafe};
+
+        let mut first = Arena::with_provenance_checks();
+        let mut second = Arena::with_provenance_checks();
+        let foo = first.insert("foo");
+        let bar = second.insert("bar");
+        let baz = second.insert("baz");
+        let resolved = first.resolve(foo).unwrap();
+
+        type Lookup = Box<dyn Fn(&mut Arena<&'static str>)>;
+        let lookups: Vec<Lookup> = vec![
+            Box::new(move |arena| {
+                let _ = arena.resolve(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.apply_resolved_mut(resolved);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.get_with_generation(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.replace_if_version(foo, 0, "qux");
+            }),
+            Box::new(move |arena| {
+                let _ = arena.remove(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.invalidate(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.try_invalidate(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.fulfill(foo, "qux");
+            }),
+            Box::new(move |arena| {
+                let _ = arena.forensics(foo);
+            }),
+            Box::new(move |arena| {
+                let _ = arena.get_mut_and_refs(bar, [foo]);
+            }),
+            Box::new(move |arena| {
+                if let Some((_, _, rest)) = arena.get2_mut_and_rest(bar, baz) {
+                    rest.get(foo);
+                }
+            }),
+            Box::new(move |arena| {
+                arena.clone().freeze().get(foo);
+            }),
+            Box::new(move |arena| {
+                let mapped = arena.try_map(|_, &value| Ok::<_, ()>(value)).unwrap();
+                mapped.get(foo);
+            }),
+            Box::new(move |arena| {
+                let raw = arena.clone().into_raw_parts();
+                unsafe { Arena::from_raw_parts(raw) }.get(foo);
+            }),
+        ];
+
+        for (i, lookup) in lookups.iter().enumerate() {
+            let result = catch_unwind(AssertUnwindSafe(|| lookup(&mut second)));
+            assert!(result.is_err(), "lookup {} accepted a foreign index", i);
+        }
+
+        // Arenas derived from `second` still accept its own indices.
+        assert_eq!(second.clone().freeze().get(bar), Some(&"bar"));
+        let raw = second.clone().into_raw_parts();
+        assert_eq!(unsafe { Arena::from_raw_parts(raw) }.get(bar), Some(&"bar"));
+        assert_eq!(Arena::with_shape_of(&second, |_| 0).get(baz), Some(&0));
+    }
+
+    #[test]
+    fn get_by_slot_after_remove() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        let slot = foo.slot();
+        assert!(!arena.was_recently_freed(slot));
+
+        arena.remove(foo);
+        assert_eq!(arena.get_by_slot(slot), None);
+        assert!(arena.was_recently_freed(slot));
+
+        let bar = arena.insert("bar");
+        assert_eq!(arena.get_by_slot(slot), Some((bar, &"bar")));
+        assert!(!arena.was_recently_freed(slot));
+        assert!(!arena.was_recently_freed(100));
+    }
+
+    #[test]
+    fn was_recently_freed_padding() {
+        let mut arena = Arena::with_slot_capacity(8);
+        assert!((0..8).all(|slot| !arena.was_recently_freed(slot)));
+
+        arena.fill_block(2, |slot| slot);
+        let far = arena.insert_if_slot_free(5, 5).unwrap();
+        assert!(!arena.was_recently_freed(2));
+        assert!(!arena.was_recently_freed(4));
+
+        arena.remove(far);
+        assert!(arena.was_recently_freed(5));
+        assert!(!arena.was_recently_freed(4));
+
+        // Once a padding slot has held a value, it counts as freed like any
+        // other.
+        let padded = arena.insert_if_slot_free(3, 3).unwrap();
+        arena.remove(padded);
+        assert!(arena.was_recently_freed(3));
+        assert!(!arena.was_recently_freed(2));
+    }
+
+    #[test]
+    fn top_k_by() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = [5, 1, 9, 3, 7, 2]
+            .iter()
+            .map(|&i| arena.insert(i))
+            .collect();
+
+        let top = arena.top_k_by(3, |a, b| a.cmp(b));
+        assert_eq!(
+            top,
+            vec![(indices[2], &9), (indices[4], &7), (indices[0], &5)]
+        );
+
+        let lowest = arena.top_k_by(2, |a, b| b.cmp(a));
+        assert_eq!(lowest, vec![(indices[1], &1), (indices[5], &2)]);
+
+        let all: Vec<_> = arena
+            .top_k_by(10, |a, b| a.cmp(b))
+            .into_iter()
+            .map(|(_, &value)| value)
+            .collect();
+        assert_eq!(all, vec![9, 7, 5, 3, 2, 1]);
+        assert!(arena.top_k_by(0, |a, b| a.cmp(b)).is_empty());
+
+        // Entries arriving after the heap fills up displace smaller ones.
+        let mut arena = Arena::new();
+        for i in 0..100u32 {
+            arena.insert(i.wrapping_mul(37) % 100);
+        }
+        let top: Vec<_> = arena
+            .top_k_by(4, |a, b| a.cmp(b))
+            .into_iter()
+            .map(|(_, &value)| value)
+            .collect();
+        assert_eq!(top, vec![99, 98, 97, 96]);
+    }
+
+    #[test]
+    fn len_u64() {
+        let mut arena = Arena::new();
+        assert_eq!(arena.len_u64(), 0);
+        assert_eq!(arena.total_slots_u64(), 0);
+
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+        arena.reserve_placeholder();
+        arena.remove(indices[3]);
+
+        assert_eq!(arena.len_u64(), arena.len() as u64);
+        assert_eq!(arena.len_u64(), 3);
+        assert_eq!(arena.total_slots_u64(), arena.storage.len() as u64);
+        assert_eq!(arena.total_slots_u64(), 5);
+    }
+
+    #[test]
+    fn insert_with_headroom() {
+        let mut arena = Arena::with_capacity(4);
+        let (first, headroom) = arena.insert_with_headroom(0);
+        assert_eq!(headroom, 3);
+        assert_eq!(arena.insert_with_headroom(1).1, 2);
+
+        arena.remove(first);
+        assert_eq!(arena.insert_with_headroom(2).1, 2);
+        assert_eq!(arena.insert_with_headroom(3).1, 1);
+        assert_eq!(arena.insert_with_headroom(4).1, 0);
+
+        arena.reserve(10);
+        let expected = arena.capacity() - arena.len() - 1;
+        assert_eq!(arena.insert_with_headroom(5).1, expected);
+    }
+
+    #[test]
+    fn insert_with_headroom_placeholders() {
+        fn scanned_headroom<T>(arena: &Arena<T>) -> usize {
+            let empty = arena
+                .storage
+                .iter()
+                .filter(|entry| entry.get_empty().is_some())
+                .count();
+            arena.storage.capacity() - arena.storage.len() + empty
+        }
+
+        let mut arena = Arena::with_capacity(8);
+        let first = arena.reserve_placeholder();
+        let second = arena.reserve_placeholder();
+        let (_, headroom) = arena.insert_with_headroom(0);
+        assert_eq!(headroom, 5);
+        assert_eq!(headroom, scanned_headroom(&arena));
+
+        arena.fulfill(first, 1).unwrap();
+        let (_, headroom) = arena.insert_with_headroom(2);
+        assert_eq!(headroom, 4);
+        assert_eq!(headroom, scanned_headroom(&arena));
+
+        // A failed `reserve_then_fill` releases its placeholders again.
+        let result = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            arena.reserve_then_fill(2, |_| Vec::new());
+        }));
+        assert!(result.is_err());
+        let (third, headroom) = arena.insert_with_headroom(3);
+        assert_eq!(headroom, scanned_headroom(&arena));
+
+        // Shrinking an empty arena drops the placeholders past the new end.
+        arena.remove(third);
+        assert_eq!(arena.reserve_placeholder().slot(), third.slot());
+        arena.drain_and_resize(2).for_each(drop);
+        assert_eq!(arena.placeholders, 1);
+        arena.fulfill(second, 4).unwrap();
+        let (_, headroom) = arena.insert_with_headroom(5);
+        assert_eq!(headroom, scanned_headroom(&arena));
+
+        let mut arena = arena.clone_with_capacity(16);
+        arena.reserve_placeholder();
+        let (_, headroom) = arena.insert_with_headroom(6);
+        assert_eq!(headroom, scanned_headroom(&arena));
+    }
+
+    #[test]
+    fn export_import_state() {
+        let mut host = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| host.insert(i)).collect();
+        host.remove(indices[3]);
+        host.remove(indices[1]);
+        let reused = host.insert(10);
+        host.remove(reused);
+        host.remove(indices[4]);
+
+        // The peer holds the same values in the same slots, but got there by a
+        // different history.
+        let mut peer = Arena::new();
+        let peer_indices: Vec<_> = (0..5).map(|i| peer.insert(i)).collect();
+        for &i in &[4, 1, 3] {
+            peer.remove(peer_indices[i]);
+        }
+
+        let state = host.export_state();
+        assert_eq!(state.free_list, vec![4, 1, 3]);
+        peer.import_state(state.clone()).unwrap();
+        assert_eq!(peer.export_state(), state);
+
+        for i in 0..5 {
+            assert_eq!(host.insert(i), peer.insert(i));
+        }
+        assert_eq!(peer.get(indices[0]), Some(&0));
+    }
+
+    #[test]
+    fn import_state_errors() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        arena.insert("bar");
+        arena.remove(foo);
+
+        let mut state = arena.export_state();
+        state.generations[1] = 0;
+        assert_eq!(
+            arena.import_state(state),
+            Err(ImportStateError::InvalidGeneration { slot: 1 })
+        );
+
+        let mut state = arena.export_state();
+        state.occupied.swap(0, 1);
+        state.free_list = vec![1];
+        assert_eq!(
+            arena.import_state(state),
+            Err(ImportStateError::OccupancyMismatch { slot: 0 })
+        );
+
+        let mut state = arena.export_state();
+        state.free_list.push(0);
+        assert_eq!(
+            arena.import_state(state),
+            Err(ImportStateError::InvalidFreeList)
+        );
+
+        let mut state = arena.export_state();
+        state.occupied.pop();
+        assert_eq!(
+            arena.import_state(state),
+            Err(ImportStateError::Inconsistent)
+        );
+    }
+
+    #[test]
+    fn clear_range() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+
+        arena.clear_range(2..5);
+        assert_eq!(arena.len(), 5);
+        for (slot, &index) in indices.iter().enumerate() {
+            assert_eq!(arena.contains(index), !(2..5).contains(&slot));
+        }
+
+        arena.clear_range(6..=100);
+        assert_eq!(arena.len(), 3);
+        assert!(arena.contains(indices[5]));
+
+        arena.clear_range(..);
+        assert!(arena.is_empty());
+    }
+
+    #[test]
+    fn index_compatible_with() {
+        let mut names = Arena::new();
+        let mut sizes = Arena::new();
+        assert!(names.index_compatible_with(&sizes));
+
+        let name = names.insert("foo");
+        let size = sizes.insert(3);
+        names.insert("bar");
+        sizes.insert(3);
+        names.remove(name);
+        sizes.remove(size);
+        assert!(names.index_compatible_with(&sizes));
+        assert!(sizes.index_compatible_with(&names));
+
+        names.insert("baz");
+        assert!(!names.index_compatible_with(&sizes));
+
+        let size = sizes.insert(3);
+        assert!(names.index_compatible_with(&sizes));
+
+        sizes.invalidate(size);
+        assert!(!names.index_compatible_with(&sizes));
+    }
+
+    #[test]
+    fn get2_mut_and_rest() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let middle = arena.insert(10);
+        let b = arena.insert(2);
+        let after = arena.insert(100);
+        let stale = arena.insert(0);
+        arena.remove(stale);
+
+        let (b_value, a_value, rest) = arena.get2_mut_and_rest(b, a).unwrap();
+        *a_value += rest.get(middle).unwrap();
+        *b_value += rest.get(after).unwrap();
+        assert_eq!(rest.get(a), None);
+        assert_eq!(rest.get(b), None);
+        assert!(!rest.contains(stale));
+
+        assert_eq!(arena[a], 11);
+        assert_eq!(arena[b], 102);
+        assert!(arena.get2_mut_and_rest(a, stale).is_none());
+    }
+
+    #[test]
+    fn fill() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i.to_string())).collect();
+        arena.remove(indices[2]);
+
+        arena.fill("reset".to_owned());
+
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.get(indices[2]), None);
+        for &i in &[0, 1, 3] {
+            assert_eq!(arena[indices[i]], "reset");
+        }
+    }
+
+    #[test]
+    fn reuse_histogram() {
+        let mut arena = Arena::new();
+        let mut churn = arena.insert(0);
+        arena.insert(1);
+        let removed = arena.insert(2);
+        arena.remove(removed);
+
+        for i in 0..3 {
+            arena.remove(churn);
+            churn = arena.insert(i);
+        }
+
+        assert_eq!(arena.reuse_histogram(), vec![3, 0, 0]);
+    }
+
+    #[test]
+    fn iter_mut_from() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+
+        let mut resume = None;
+        for (index, value) in arena.iter_mut_from(resume).take(3) {
+            *value += 10;
+            resume = Some(index.slot() + 1);
+        }
+        assert_eq!(resume, Some(4));
+
+        for (_, value) in arena.iter_mut_from(resume) {
+            *value += 10;
+        }
+
+        let values: Vec<_> = arena.iter().map(|(_, &value)| value).collect();
+        assert_eq!(values, vec![10, 11, 13, 14, 15]);
+        assert_eq!(arena.iter_mut_from(Some(100)).count(), 0);
+    }
+
+    #[test]
+    fn structural_fingerprint() {
+        let mut first = Arena::new();
+        let mut second = Arena::new();
+        let first_indices: Vec<_> = (0..4).map(|i| first.insert(i)).collect();
+        let second_indices: Vec<_> = (0..4).map(|i| second.insert(i * 100)).collect();
+        assert_eq!(
+            first.structural_fingerprint(),
+            second.structural_fingerprint()
+        );
+
+        first.remove(first_indices[1]);
+        first.remove(first_indices[2]);
+        second.remove(second_indices[1]);
+        second.remove(second_indices[2]);
+        assert_eq!(
+            first.structural_fingerprint(),
+            second.structural_fingerprint()
+        );
+
+        // Same empty slots, but they'll be reused in a different order.
+        second.rebuild_free_list_ascending();
+        assert_eq!(
+            first.export_state().occupied,
+            second.export_state().occupied
+        );
+        assert_ne!(
+            first.structural_fingerprint(),
+            second.structural_fingerprint()
+        );
+    }
+
+    #[test]
+    fn remove_compacting() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+
+        assert_eq!(arena.remove_compacting(indices[1]), Some(1));
+        assert_eq!(arena.storage.len(), 5);
+
+        assert_eq!(arena.remove_compacting(indices[4]), Some(4));
+        assert_eq!(arena.storage.len(), 4);
+        assert_eq!(arena.remove_compacting(indices[3]), Some(3));
+        assert_eq!(arena.storage.len(), 3);
+
+        // Removing slot 2 also releases the hole left at slot 1.
+        assert_eq!(arena.remove_compacting(indices[2]), Some(2));
+        assert_eq!(arena.storage.len(), 1);
+        assert_eq!(arena.free_list_snapshot(), Vec::<u32>::new());
+
+        assert_eq!(arena.remove_compacting(indices[2]), None);
+        assert_eq!(arena.len(), 1);
+    }
+
+    #[test]
+    fn iter_checked() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+        arena.remove(indices[3]);
+        assert_eq!(arena.validate(), Ok(()));
+        assert!(arena.iter_checked().all(|item| item.is_ok()));
+
+        // Point slot 1's free list link past the end of storage.
+        match &mut arena.storage[1] {
+            Entry::Empty(empty) => empty.next_free = Some(FreePointer::from_slot(100)),
+            _ => unreachable!(),
         }
+
+        let items: Vec<_> = arena.iter_checked().collect();
+        assert_eq!(
+            items,
+            vec![
+                Ok((indices[0], &0)),
+                Err(CorruptionError::FreePointerOutOfRange { slot: Some(1) }),
+            ]
+        );
+        assert_eq!(
+            arena.validate(),
+            Err(CorruptionError::FreePointerOutOfRange { slot: Some(1) })
+        );
     }
-}
 
-impl<'a, T> IntoIterator for &'a Arena<T> {
-    type Item = (Index, &'a T);
-    type IntoIter = Iter<'a, T>;
+    #[test]
+    fn validate() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[0]);
+        arena.remove(indices[2]);
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter()
-    }
-}
+        arena.len = 3;
+        assert_eq!(
+            arena.validate(),
+            Err(CorruptionError::LengthMismatch {
+                len: 3,
+                occupied: 2
+            })
+        );
+        arena.len = 2;
 
-impl<'a, T> IntoIterator for &'a mut Arena<T> {
-    type Item = (Index, &'a mut T);
-    type IntoIter = IterMut<'a, T>;
+        // Drop slot 0 from the free list.
+        arena.first_free = Some(FreePointer::from_slot(2));
+        match &mut arena.storage[2] {
+            Entry::Empty(empty) => empty.next_free = None,
+            _ => unreachable!(),
+        }
+        assert_eq!(arena.validate(), Err(CorruptionError::FreeListMismatch));
 
-    fn into_iter(self) -> Self::IntoIter {
-        self.iter_mut()
+        // Make the free list loop.
+        match &mut arena.storage[2] {
+            Entry::Empty(empty) => empty.next_free = Some(FreePointer::from_slot(2)),
+            _ => unreachable!(),
+        }
+        assert_eq!(arena.validate(), Err(CorruptionError::FreeListMismatch));
+
+        arena.first_free = Some(FreePointer::from_slot(1));
+        assert_eq!(
+            arena.validate(),
+            Err(CorruptionError::FreePointerToLiveEntry { slot: None })
+        );
     }
-}
 
-impl<T> ops::Index<Index> for Arena<T> {
-    type Output = T;
+    #[test]
+    fn get_by_bits() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        let bits = foo.to_bits();
 
-    fn index(&self, index: Index) -> &Self::Output {
-        self.get(index)
-            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
-    }
-}
+        assert_eq!(arena.get_by_bits(bits), Some(&"foo"));
+        *arena.get_by_bits_mut(bits).unwrap() = "bar";
+        assert_eq!(arena[foo], "bar");
 
-impl<T> ops::IndexMut<Index> for Arena<T> {
-    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
-        self.get_mut(index)
-            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+        arena.remove(foo);
+        arena.insert("baz");
+        assert_eq!(arena.get_by_bits(bits), None);
+        assert_eq!(arena.get_by_bits_mut(bits), None);
+
+        assert_eq!(arena.get_by_bits(0), None);
+        assert_eq!(arena.get_by_bits_mut(u64::from(foo.slot())), None);
     }
-}
 
-#[cfg(test)]
-mod test {
-    use super::{Arena, Index};
+    #[test]
+    fn try_map() {
+        let mut raw = Arena::new();
+        let one = raw.insert("1");
+        let removed = raw.insert("x");
+        let two = raw.insert("2");
+        raw.remove(removed);
 
-    use std::mem::size_of;
+        let parsed = raw.try_map(|_, value| value.parse::<u32>()).unwrap();
+        assert_eq!(parsed.len(), 2);
+        assert_eq!(parsed.get(one), Some(&1));
+        assert_eq!(parsed.get(two), Some(&2));
+        assert_eq!(parsed.get(removed), None);
 
-    #[test]
-    fn size_of_index() {
-        assert_eq!(size_of::<Index>(), 8);
-        assert_eq!(size_of::<Option<Index>>(), 8);
+        raw.insert("bad");
+        let mut visited = 0;
+        let result = raw.try_map(|_, value| {
+            visited += 1;
+            value.parse::<u32>()
+        });
+        assert!(result.is_err());
+        assert_eq!(visited, 2);
     }
 
     #[test]
-    fn new() {
-        let arena: Arena<u32> = Arena::new();
-        assert_eq!(arena.len(), 0);
-        assert_eq!(arena.capacity(), 0);
+    fn fill_block() {
+        let mut arena = Arena::new();
+        let first = arena.insert(0);
+        arena.insert(1);
+        arena.remove(first);
+
+        let (start, indices) = arena.fill_block(4, |slot| slot * 10);
+        assert_eq!(start, 2);
+        assert_eq!(indices.len(), 4);
+        for (offset, &index) in (0..).zip(&indices) {
+            assert_eq!(index.slot(), start + offset);
+            assert_eq!(arena[index], index.slot() * 10);
+        }
+
+        assert_eq!(arena.len(), 5);
+        assert_eq!(arena.free_list_snapshot(), vec![0]);
+        assert_eq!(arena.fill_block(0, |slot| slot), (6, Vec::new()));
     }
 
     #[test]
-    fn with_capacity() {
-        let arena: Arena<u32> = Arena::with_capacity(8);
+    fn with_slot_capacity() {
+        let mut arena = Arena::with_slot_capacity(16);
         assert_eq!(arena.len(), 0);
-        assert_eq!(arena.capacity(), 8);
+        assert_eq!(arena.total_slots_u64(), 0);
+        assert_eq!(arena.free_list_snapshot().len(), 0);
+        assert!(arena.capacity() >= 16);
+
+        let first = arena.insert(0);
+        assert_eq!(first, Index::from_bits(1 << 32));
     }
 
     #[test]
-    fn insert_and_get() {
+    fn epoch() {
         let mut arena = Arena::new();
+        assert_eq!(arena.epoch(), 0);
 
         let one = arena.insert(1);
-        assert_eq!(arena.len(), 1);
-        assert_eq!(arena.get(one), Some(&1));
-
         let two = arena.insert(2);
-        assert_eq!(arena.len(), 2);
-        assert_eq!(arena.get(one), Some(&1));
-        assert_eq!(arena.get(two), Some(&2));
+        let epoch = arena.epoch();
+        assert_eq!(epoch, 2);
+
+        // Reads and in-place mutation don't advance the epoch.
+        assert_eq!(arena.get_with_epoch(one), Some((&1, epoch)));
+        assert_eq!(arena.iter().count(), 2);
+        *arena.get_mut(two).unwrap() = 20;
+        assert_eq!(arena.epoch(), epoch);
+
+        let one_prime = arena.invalidate(one).unwrap();
+        assert!(arena.epoch() > epoch);
+        let epoch = arena.epoch();
+
+        arena.remove(two);
+        assert!(arena.epoch() > epoch);
+        let epoch = arena.epoch();
+
+        // Failed operations leave the epoch alone.
+        assert_eq!(arena.remove(two), None);
+        assert_eq!(arena.invalidate(one), None);
+        assert_eq!(arena.get_with_epoch(one), None);
+        assert_eq!(arena.get_with_epoch(one_prime), Some((&1, epoch)));
     }
 
     #[test]
-    fn insert_remove_get() {
+    fn indices_where() {
         let mut arena = Arena::new();
-        let one = arena.insert(1);
+        let indices: Vec<_> = (0..8).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[4]);
 
-        let two = arena.insert(2);
-        assert_eq!(arena.len(), 2);
-        assert!(arena.contains(two));
-        assert_eq!(arena.remove(two), Some(2));
-        assert!(!arena.contains(two));
+        let evens = arena.indices_where(|_, &value| value % 2 == 0);
+        assert_eq!(evens, vec![indices[0], indices[2], indices[6]]);
+        assert_eq!(arena.indices_where(|_, &value| value > 100), Vec::new());
+    }
 
-        let three = arena.insert(3);
-        assert_eq!(arena.len(), 2);
-        assert_eq!(arena.get(one), Some(&1));
-        assert_eq!(arena.get(three), Some(&3));
-        assert_eq!(arena.get(two), None);
+    #[test]
+    fn insert_and_then() {
+        struct Node {
+            children: Vec<Index>,
+        }
+
+        let mut arena = Arena::new();
+        let root = arena.insert(Node {
+            children: Vec::new(),
+        });
+
+        let child = arena.insert_and_then(
+            Node {
+                children: Vec::new(),
+            },
+            |index, arena| {
+                assert!(arena.contains(index));
+                arena[root].children.push(index);
+            },
+        );
+
+        assert_eq!(arena[root].children, vec![child]);
+        assert!(arena[child].children.is_empty());
     }
 
     #[test]
-    fn insert_remove_get_by_slot() {
+    fn retain_spawning() {
         let mut arena = Arena::new();
-        let one = arena.insert(1);
+        let enemies: Vec<_> = (0..4).map(|i| arena.insert(i)).collect();
 
-        let two = arena.insert(2);
-        assert_eq!(arena.len(), 2);
-        assert!(arena.contains(two));
-        assert_eq!(arena.remove_by_slot(two.slot()), Some((two, 2)));
-        assert!(!arena.contains(two));
-        assert_eq!(arena.get_by_slot(two.slot()), None);
+        let loot = arena.retain_spawning(|_, value, spawned| {
+            if *value % 2 == 1 {
+                spawned.push(*value * 10);
+                false
+            } else {
+                true
+            }
+        });
+
+        assert_eq!(loot.len(), 2);
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.get(enemies[0]), Some(&0));
+        assert_eq!(arena.get(enemies[1]), None);
+        assert_eq!(arena.get(enemies[2]), Some(&2));
+        assert_eq!(arena.get(enemies[3]), None);
+
+        let mut values: Vec<_> = loot.iter().map(|&index| arena[index]).collect();
+        values.sort_unstable();
+        assert_eq!(values, vec![10, 30]);
+    }
 
+    #[test]
+    fn get_many() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let two = arena.insert(2);
         let three = arena.insert(3);
-        assert_eq!(arena.len(), 2);
-        assert_eq!(arena.get(one), Some(&1));
-        assert_eq!(arena.get(three), Some(&3));
-        assert_eq!(arena.get(two), None);
-        assert_eq!(arena.get_by_slot(two.slot()), Some((three, &3)));
+        arena.remove(two);
+
+        assert_eq!(
+            arena.get_many(&[three, two, one, three]),
+            vec![Some(&3), None, Some(&1), Some(&3)]
+        );
+        assert_eq!(arena.get_many(&[]), Vec::<Option<&i32>>::new());
     }
 
     #[test]
-    fn get_mut() {
+    fn to_sorted_vec_by() {
         let mut arena = Arena::new();
-        let foo = arena.insert(5);
+        for &value in &[5, 3, 9, 1, 7] {
+            arena.insert(value);
+        }
+        let removed = arena.insert(4);
+        arena.remove(removed);
 
-        let handle = arena.get_mut(foo).unwrap();
-        *handle = 6;
+        let (values, indices) = arena.to_sorted_vec_by(|a, b| a.cmp(b));
+        assert_eq!(values, vec![1, 3, 5, 7, 9]);
+        assert_eq!(indices.len(), values.len());
+        for (value, &index) in values.iter().zip(&indices) {
+            assert_eq!(arena.get(index), Some(value));
+        }
+    }
 
-        assert_eq!(arena.get(foo), Some(&6));
+    #[test]
+    #[should_panic(
+        expected = "No entry at index Index { slot: 0, generation: Generation(1) }: slot is occupied with generation 2"
+    )]
+    fn index_stale_panic_message() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        arena.remove(foo);
+        arena.insert("bar");
+
+        let _ = arena[foo];
     }
 
     #[test]
-    fn insert_remove_insert_capacity() {
-        let mut arena = Arena::with_capacity(2);
-        assert_eq!(arena.capacity(), 2);
+    #[should_panic(
+        expected = "No entry at index Index { slot: 0, generation: Generation(1) }: slot is empty, last occupied with generation 1"
+    )]
+    fn index_mut_empty_panic_message() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        arena.remove(foo);
+
+        arena[foo] = "bar";
+    }
+
+    #[test]
+    fn slots_overlap() {
+        let mut evens = Arena::new();
+        let mut odds = Arena::new();
+        for i in 0..6 {
+            evens.insert(i);
+            odds.insert(i);
+        }
+        evens.retain(|index, _| index.slot() % 2 == 0);
+        odds.retain(|index, _| index.slot() % 2 == 1);
+
+        assert!(!evens.slots_overlap(&odds));
+        assert!(!odds.slots_overlap(&Arena::<()>::new()));
+
+        evens.insert(100);
+        assert!(evens.slots_overlap(&odds));
+        assert!(odds.slots_overlap(&evens));
+    }
 
+    #[test]
+    fn swap_by_slot() {
+        let mut arena = Arena::new();
         let a = arena.insert("a");
         let b = arena.insert("b");
-        assert_eq!(arena.len(), 2);
-        assert_eq!(arena.capacity(), 2);
+        let c = arena.insert("c");
+        arena.remove(c);
+
+        assert!(arena.swap_by_slot(a.slot(), b.slot()));
+        assert_eq!(arena.get_by_slot(a.slot()), Some((a, &"b")));
+        assert_eq!(arena.get_by_slot(b.slot()), Some((b, &"a")));
+
+        assert!(arena.swap_by_slot(a.slot(), a.slot()));
+        assert!(!arena.swap_by_slot(a.slot(), c.slot()));
+        assert!(!arena.swap_by_slot(a.slot(), 10));
+        assert_eq!(arena[a], "b");
+    }
+
+    #[test]
+    fn merge_preserving() {
+        let mut evens = Arena::new();
+        let mut odds = Arena::new();
+        let indices: Vec<_> = (0..6)
+            .map(|i| {
+                odds.insert(i);
+                evens.insert(i)
+            })
+            .collect();
+        evens.retain(|index, _| index.slot() % 2 == 0);
+        odds.retain(|index, _| index.slot() % 2 == 1);
+        let extra = odds.insert_if_slot_free(8, 8).unwrap();
+
+        let merged = evens.clone().merge_preserving(odds.clone()).unwrap();
+        assert_eq!(merged.len(), 7);
+        assert_eq!(merged.validate(), Ok(()));
+        for (i, &index) in indices.iter().enumerate() {
+            assert_eq!(merged.get(index), Some(&i));
+        }
+        assert_eq!(merged.get(extra), Some(&8));
+
+        evens.insert(100);
+        assert_eq!(
+            evens.merge_preserving(odds).unwrap_err(),
+            MergeError::SlotCollision { slot: 5 }
+        );
+    }
+
+    #[test]
+    fn merge_preserving_placeholder_collision() {
+        let mut ours = Arena::new();
+        let mut theirs = Arena::new();
+        ours.insert(0);
+        theirs.insert(0);
+        theirs.remove_by_slot(0);
+        theirs.reserve_placeholder();
+
+        // A placeholder in one arena and a value in the other count as an
+        // overlap for both checks.
+        assert!(ours.slots_overlap(&theirs));
+        assert!(theirs.slots_overlap(&ours));
+        assert_eq!(
+            ours.merge_preserving(theirs).unwrap_err(),
+            MergeError::SlotCollision { slot: 0 }
+        );
+    }
+
+    #[test]
+    fn merge_preserving_reissues_entity_ids() {
+        let mut ours = Arena::new();
+        let mut theirs = Arena::new();
+        ours.enable_entity_ids();
+        theirs.enable_entity_ids();
+
+        let a = ours.insert("a");
+        theirs.insert_if_slot_free(1, "b").unwrap();
+        let c = theirs.insert_if_slot_free(2, "c").unwrap();
+        assert_eq!(theirs.entity_id(c), Some(2));
+
+        let mut merged = ours.merge_preserving(theirs).unwrap();
+        assert_eq!(merged.entity_id(a), Some(1));
+        assert_eq!(merged.entity_id(c), Some(3));
+
+        let d = merged.insert("d");
+        assert_eq!(merged.entity_id(d), Some(4));
+    }
+
+    #[test]
+    fn resolve() {
+        let mut arena = Arena::new();
+        let a = arena.insert(1);
+        let b = arena.insert(2);
+        let c = arena.insert(3);
+
+        let commands: Vec<(ResolvedRef, i32)> = vec![
+            (arena.resolve(a).unwrap(), 10),
+            (arena.resolve(b).unwrap(), 20),
+            (arena.resolve(c).unwrap(), 30),
+        ];
+        assert_eq!(commands[1].0.index(), b);
 
         arena.remove(a);
-        arena.remove(b);
-        assert_eq!(arena.len(), 0);
-        assert_eq!(arena.capacity(), 2);
+        arena.insert(4);
+        arena.invalidate(b);
 
-        let _a2 = arena.insert("a2");
-        let _b2 = arena.insert("b2");
-        assert_eq!(arena.len(), 2);
-        assert_eq!(arena.capacity(), 2);
+        let applied: Vec<bool> = commands
+            .into_iter()
+            .map(
+                |(resolved, value)| match arena.apply_resolved_mut(resolved) {
+                    Some(slot) => {
+                        *slot = value;
+                        true
+                    }
+                    None => false,
+                },
+            )
+            .collect();
+
+        assert_eq!(applied, vec![false, false, true]);
+        assert_eq!(arena[c], 30);
+        assert_eq!(
+            arena.get_by_slot(a.slot()).map(|(_, &value)| value),
+            Some(4)
+        );
+
+        arena.remove(c);
+        assert_eq!(arena.resolve(c), None);
     }
 
     #[test]
-    fn invalidate() {
+    fn iter_ordered() {
         let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        let c = arena.insert("c");
+        arena.remove(b);
+
+        let order = vec![c, b, a, c];
+        assert_eq!(
+            arena
+                .iter_ordered(order.iter().copied())
+                .collect::<Vec<_>>(),
+            vec![(c, &"c"), (a, &"a"), (c, &"c")]
+        );
+        assert_eq!(arena.iter_ordered(order).count(), 3);
+    }
 
+    #[test]
+    fn iter_filter() {
+        let mut arena = Arena::new();
         let a = arena.insert("a");
-        assert_eq!(arena.get(a), Some(&"a"));
+        let b = arena.insert("b");
+        arena.insert("c");
+        arena.remove(b);
+        let a = arena.invalidate(a).unwrap();
+        let d = arena.insert("d");
 
-        let new_a = arena.invalidate(a).unwrap();
-        assert_eq!(arena.get(a), None);
-        assert_eq!(arena.get(new_a), Some(&"a"));
+        let mut recent: Vec<_> = arena
+            .iter_filter(|index, _| index.generation.to_u32() > 1)
+            .collect();
+        recent.sort_by_key(|(index, _)| index.slot());
+        assert_eq!(recent, vec![(a, &"a"), (d, &"d")]);
+
+        let none = arena.iter_filter(|_, &value| value == "b");
+        assert_eq!(none.count(), 0);
     }
 
     #[test]
-    fn retain() {
+    fn reserve_then_fill() {
+        struct Node {
+            name: &'static str,
+            next: Index,
+        }
+
         let mut arena = Arena::new();
+        let removed = arena.reserve_then_fill(1, |indices| {
+            vec![Node {
+                name: "removed",
+                next: indices[0],
+            }]
+        });
+        arena.remove(removed[0]);
 
-        for i in 0..100 {
+        let nodes = arena.reserve_then_fill(3, |indices| {
+            let names = ["a", "b", "c"];
+            (0..3)
+                .map(|i| Node {
+                    name: names[i],
+                    next: indices[(i + 1) % 3],
+                })
+                .collect::<Vec<_>>()
+        });
+
+        assert_eq!(arena.len(), 3);
+        let mut current = nodes[0];
+        let mut visited = Vec::new();
+        for _ in 0..4 {
+            visited.push(arena[current].name);
+            current = arena[current].next;
+        }
+        assert_eq!(visited, vec!["a", "b", "c", "a"]);
+    }
+
+    #[test]
+    #[should_panic(expected = "expected 3 values, but got 2")]
+    fn reserve_then_fill_wrong_count() {
+        let mut arena = Arena::new();
+        arena.reserve_then_fill(3, |_| vec![1, 2]);
+    }
+
+    #[test]
+    fn mixed_hash() {
+        use std::collections::HashSet;
+
+        let indices: Vec<_> = (0..100)
+            .map(|slot| Index {
+                slot,
+                generation: Generation::first(),
+            })
+            .collect();
+
+        let low: HashSet<_> = indices.iter().map(|i| i.mixed_hash() % 128).collect();
+        let high: HashSet<_> = indices.iter().map(|i| i.mixed_hash() >> 57).collect();
+
+        // 100 random keys fill about 70 of 128 buckets on average.
+        assert!(low.len() >= 60, "only {} low buckets used", low.len());
+        assert!(high.len() >= 60, "only {} high buckets used", high.len());
+    }
+
+    #[test]
+    fn clone_with_capacity() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..5).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+
+        let mut clone = arena.clone_with_capacity(64);
+        assert!(clone.capacity() >= 64);
+        assert_eq!(clone.len(), arena.len());
+        assert_eq!(
+            clone.iter().collect::<Vec<_>>(),
+            arena.iter().collect::<Vec<_>>()
+        );
+        assert_eq!(clone.free_list_snapshot(), arena.free_list_snapshot());
+        assert_eq!(clone.insert(10), arena.insert(10));
+
+        // Asking for less than the arena needs still clones every slot.
+        let small = arena.clone_with_capacity(0);
+        assert_eq!(small.len(), arena.len());
+        assert!(small.capacity() >= 5);
+    }
+
+    #[test]
+    fn reset() {
+        let mut arena = Arena::with_capacity(8);
+        let mut fresh = Arena::<i32>::new();
+        for i in 0..8 {
             arena.insert(i);
         }
+        let removed = arena.insert(8);
+        arena.remove(removed);
+        let capacity = arena.capacity();
 
-        arena.retain(|_, &mut i| i % 2 == 1);
+        arena.reset();
+        assert!(arena.is_empty());
+        assert_eq!(arena.capacity(), capacity);
+        assert_eq!(arena.peak_len(), 0);
+        assert!(arena.free_list_snapshot().is_empty());
 
-        for (_, i) in arena.iter() {
-            assert_eq!(i % 2, 1);
+        for i in 0..3 {
+            assert_eq!(arena.insert(i), fresh.insert(i));
         }
+    }
 
-        assert_eq!(arena.len(), 50);
+    #[test]
+    fn reset_clears_bookkeeping() {
+        use std::sync::{Arc, Mutex};
+
+        let warnings = Arc::new(Mutex::new(Vec::new()));
+        let mut arena = Arena::<Vec<u8>>::new();
+        let sink = Arc::clone(&warnings);
+        arena.set_generation_warning(1, Box::new(move |slot, _| sink.lock().unwrap().push(slot)));
+        arena.enable_entity_ids();
+        arena.set_auto_trim(true);
+
+        let tagged = arena.insert_tagged(vec![1], 3);
+        let removed = arena.insert(vec![2]);
+        assert_eq!(arena.replace_if_version(tagged, 0, vec![3]), Ok(1));
+        assert!(arena.remove_recycling(removed));
+        arena.remove(tagged);
+        assert_eq!(*warnings.lock().unwrap(), vec![1, 0]);
+
+        arena.reset();
+        assert!(arena.storage.is_empty());
+        assert_eq!(arena.len, 0);
+        assert_eq!(arena.peak_len, 0);
+        assert!(arena.first_free.is_none());
+        assert!(arena.recycled.is_empty());
+        assert_eq!(arena.next_entity_id.map(|id| id.get()), Some(1));
+        assert_eq!(arena.epoch(), 0);
+        assert_eq!(arena.tags.get(0), 0);
+        assert_eq!(arena.versions.get(0), 0);
+        assert_eq!(arena.entity_ids.get(0), None);
+        assert!(arena.auto_trim);
+
+        // Entity ids start over, and slots can be warned about again.
+        let first = arena.insert(Vec::new());
+        assert_eq!(arena.entity_id(first), Some(1));
+        assert_eq!(arena.iter_tagged(0).count(), 1);
+        assert_eq!(arena.get_versioned(first), Some((&Vec::new(), 0)));
+        arena.remove(first);
+        assert_eq!(*warnings.lock().unwrap(), vec![1, 0, 0]);
+    }
+
+    #[test]
+    fn get_mut_and_len() {
+        let mut arena = Arena::new();
+        let foo = arena.insert(1);
+        arena.insert(2);
+
+        let (value, len) = arena.get_mut_and_len(foo).unwrap();
+        *value *= len;
+        assert_eq!(len, arena.len());
+        assert_eq!(arena[foo], 2);
+
+        arena.remove(foo);
+        assert!(arena.get_mut_and_len(foo).is_none());
+    }
+
+    #[test]
+    fn iter_slot_masked() {
+        let mut arena = Arena::new();
+        for i in 0..7 {
+            arena.insert(i);
+        }
+
+        let evens: Vec<_> = arena.iter_slot_masked(1, 0).map(|(_, &v)| v).collect();
+        let odds: Vec<_> = arena.iter_slot_masked(1, 1).map(|(_, &v)| v).collect();
+        assert_eq!(evens, vec![0, 2, 4, 6]);
+        assert_eq!(odds, vec![1, 3, 5]);
+        assert!(arena
+            .iter_slot_masked(1, 1)
+            .all(|(index, _)| index.slot() % 2 == 1));
+    }
+
+    #[test]
+    fn free_list_snapshot() {
+        let mut arena = Arena::new();
+        assert_eq!(arena.free_list_snapshot(), Vec::<u32>::new());
+        assert_eq!(arena.first_free_slot(), None);
+
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        for &i in &[2, 4, 0] {
+            arena.remove(indices[i]);
+        }
+
+        assert_eq!(arena.free_list_snapshot(), vec![0, 4, 2]);
+        assert_eq!(arena.first_free_slot(), Some(0));
+
+        arena.insert(6);
+        assert_eq!(arena.free_list_snapshot(), vec![4, 2]);
+        assert_eq!(arena.first_free_slot(), Some(4));
+    }
+
+    #[test]
+    fn index_well_formed() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        assert!(Index::is_well_formed_bits(foo.to_bits()));
+
+        let roundtrip = Index::try_from_bits(foo.to_bits()).unwrap();
+        assert_eq!(roundtrip, foo);
+
+        for &bits in &[0, 0x0000_0000_DEAD_BEEF] {
+            assert!(!Index::is_well_formed_bits(bits));
+            assert_eq!(Index::try_from_bits(bits), None);
+        }
     }
 
     #[test]
@@ -612,6 +5748,31 @@ mod test {
         assert_eq!(index.to_bits(), 0x1BADCAFE_DEADBEEF);
     }
 
+    #[test]
+    fn index_bits_with_tag_roundtrip() {
+        let index = Index::from_bits(0x00ABCDEF_DEADBEEF);
+        let bits = index.to_bits_with_tag(0x7F);
+        assert_eq!(bits, 0x7FABCDEF_DEADBEEF);
+        assert_eq!(Index::from_bits_with_tag(bits), (0x7F, index));
+
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        assert_eq!(Index::from_bits_with_tag(foo.to_bits_with_tag(3)), (3, foo));
+    }
+
+    #[test]
+    #[should_panic(expected = "too large to be packed with a tag")]
+    fn index_bits_with_tag_panic_on_large_generation() {
+        Index::from_bits(0x01000000_DEADBEEF).to_bits_with_tag(1);
+    }
+
+    #[test]
+    #[should_panic(expected = "too large to be packed with a tag")]
+    fn index_bits_with_tag_panic_on_provenance() {
+        let mut arena = Arena::with_provenance_checks();
+        arena.insert("foo").to_bits_with_tag(1);
+    }
+
     #[test]
     #[should_panic]
     fn index_bits_panic_on_zero_generation() {
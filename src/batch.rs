@@ -0,0 +1,91 @@
+use crate::arena::{Arena, Index};
+
+/// See [`Arena::batch`].
+pub struct BatchOps<'a, T> {
+    pub(crate) arena: &'a mut Arena<T>,
+    pub(crate) ops: Vec<BatchOp<T>>,
+}
+
+pub(crate) enum BatchOp<T> {
+    Insert(T),
+    Remove(Index),
+}
+
+impl<'a, T> BatchOps<'a, T> {
+    /// Stage a value to be inserted into the arena when the batch is applied.
+    pub fn insert(&mut self, value: T) -> &mut Self {
+        self.ops.push(BatchOp::Insert(value));
+        self
+    }
+
+    /// Stage the value at the given index to be removed from the arena when
+    /// the batch is applied. Indices that aren't contained in the arena at
+    /// that point are ignored.
+    pub fn remove(&mut self, index: Index) -> &mut Self {
+        self.ops.push(BatchOp::Remove(index));
+        self
+    }
+
+    /// Apply all staged operations to the arena in the order they were staged,
+    /// returning the indices of the inserted values in the same order.
+    pub fn apply(self) -> Vec<Index> {
+        let BatchOps { arena, ops } = self;
+        let mut inserted = Vec::new();
+
+        for op in ops {
+            match op {
+                BatchOp::Insert(value) => inserted.push(arena.insert(value)),
+                BatchOp::Remove(index) => {
+                    arena.remove(index);
+                }
+            }
+        }
+
+        inserted
+    }
+
+    /// Discard all staged operations without touching the arena. Dropping the
+    /// batch has the same effect.
+    pub fn cancel(self) {}
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn apply() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+        let two = arena.insert(2);
+
+        let mut batch = arena.batch();
+        batch.insert(3).remove(one).insert(4);
+        let inserted = batch.apply();
+
+        assert_eq!(inserted.len(), 2);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.get(one), None);
+        assert_eq!(arena.get(two), Some(&2));
+        assert_eq!(arena.get(inserted[0]), Some(&3));
+        assert_eq!(arena.get(inserted[1]), Some(&4));
+    }
+
+    #[test]
+    fn cancel() {
+        let mut arena = Arena::new();
+        let one = arena.insert(1);
+
+        let mut batch = arena.batch();
+        batch.insert(2).remove(one);
+        batch.cancel();
+
+        {
+            let mut batch = arena.batch();
+            batch.insert(3).remove(one);
+        }
+
+        assert_eq!(arena.len(), 1);
+        assert_eq!(arena.get(one), Some(&1));
+    }
+}
@@ -0,0 +1,45 @@
+use roaring::RoaringBitmap;
+
+use crate::arena::{Arena, Entry};
+
+impl<T> Arena<T> {
+    /// Return the set of occupied slots as a compressed bitmap, for cheap set
+    /// operations across arenas, like finding the slots two arenas have in
+    /// common. Placeholders are not included.
+    ///
+    /// Requires the `roaring` feature.
+    pub fn occupied_bitmap(&self) -> RoaringBitmap {
+        let occupied = (0u32..)
+            .zip(self.entries())
+            .filter(|(_, entry)| matches!(entry, Entry::Occupied(_)))
+            .map(|(slot, _)| slot);
+
+        // Slots are visited in ascending order, so they can be appended in bulk.
+        RoaringBitmap::from_sorted_iter(occupied).unwrap_or_else(|_| unreachable!())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn occupied_bitmap() {
+        let mut first = Arena::new();
+        let mut second = Arena::new();
+        for i in 0..10 {
+            first.insert(i);
+            second.insert(i);
+        }
+        first.retain(|index, _| index.slot() % 2 == 0);
+        second.retain(|index, _| index.slot() % 3 == 0);
+        first.reserve_placeholder();
+
+        let bitmap = first.occupied_bitmap();
+        assert_eq!(bitmap.len(), first.len() as u64);
+        assert!(first.iter().all(|(index, _)| bitmap.contains(index.slot())));
+
+        let shared = bitmap & second.occupied_bitmap();
+        assert_eq!(shared.iter().collect::<Vec<_>>(), vec![0, 6]);
+    }
+}
@@ -2,10 +2,11 @@ use std::iter::{ExactSizeIterator, FusedIterator};
 
 use crate::arena::{Arena, Index};
 
-/// See [`Arena::drain`].
+/// See [`Arena::drain`] and [`Arena::drain_and_resize`].
 pub struct Drain<'a, T> {
     pub(crate) arena: &'a mut Arena<T>,
     pub(crate) slot: u32,
+    pub(crate) resize_to: Option<usize>,
 }
 
 impl<'a, T> Iterator for Drain<'a, T> {
@@ -32,6 +33,12 @@ impl<'a, T> Iterator for Drain<'a, T> {
             // If this entry is occupied, this method will mark it as an empty.
             // Otherwise, we'll continue looping until we've drained all
             // occupied entries from the arena.
+            //
+            // The slot is fully removed from the arena before its value is
+            // handed out, and `self.slot` has already moved past it, so if
+            // dropping the value panics and this iterator is dropped while
+            // unwinding, cleanup picks up at the next slot and never sees a
+            // half-removed entry.
             if let Some((index, value)) = self.arena.remove_by_slot(slot) {
                 return Some((index, value));
             }
@@ -50,6 +57,10 @@ impl<'a, T> Drop for Drain<'a, T> {
     // Continue iterating/dropping if there are any elements left.
     fn drop(&mut self) {
         self.for_each(drop);
+
+        if let Some(target_capacity) = self.resize_to {
+            self.arena.resize_empty(target_capacity);
+        }
     }
 }
 
@@ -58,6 +69,8 @@ mod test {
     use crate::Arena;
 
     use std::collections::HashSet;
+    use std::panic::{catch_unwind, AssertUnwindSafe};
+    use std::sync::atomic::{AtomicUsize, Ordering};
 
     #[test]
     fn drain() {
@@ -93,4 +106,66 @@ mod test {
         assert_eq!(arena.get(one), None);
         assert_eq!(arena.get(two), None);
     }
+
+    #[test]
+    fn drain_and_resize() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..10).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[2]);
+
+        let drained: Vec<_> = arena.drain_and_resize(4).collect();
+        assert_eq!(drained.len(), 9);
+        assert_eq!(arena.len(), 0);
+        assert_eq!(arena.capacity(), 4);
+
+        // Slots that survived the resize still reject their old indices.
+        for i in 0..4 {
+            let index = arena.insert(i);
+            assert!(index.slot() < 4);
+            assert_eq!(arena.get(indices[index.slot() as usize]), None);
+        }
+        assert_eq!(arena.capacity(), 4);
+
+        // Dropping the iterator early still resizes, including growing.
+        drop(arena.drain_and_resize(16));
+        assert!(arena.is_empty());
+        assert_eq!(arena.capacity(), 16);
+    }
+
+    #[test]
+    fn drain_panicking_drop() {
+        static DROPS: AtomicUsize = AtomicUsize::new(0);
+
+        struct Bomb(bool);
+
+        impl Drop for Bomb {
+            fn drop(&mut self) {
+                DROPS.fetch_add(1, Ordering::SeqCst);
+
+                if self.0 {
+                    panic!("boom");
+                }
+            }
+        }
+
+        let mut arena = Arena::new();
+        for i in 0..6 {
+            arena.insert(Bomb(i == 2));
+        }
+
+        let result = catch_unwind(AssertUnwindSafe(|| {
+            let mut drain = arena.drain();
+            drain.next();
+        }));
+        assert!(result.is_err());
+
+        // Values up to and including the one that panicked were taken out of
+        // the arena and dropped exactly once. The rest are still in the arena.
+        assert_eq!(DROPS.load(Ordering::SeqCst), 3);
+        assert_eq!(arena.len(), 3);
+        assert_eq!(arena.validate(), Ok(()));
+
+        drop(arena);
+        assert_eq!(DROPS.load(Ordering::SeqCst), 6);
+    }
 }
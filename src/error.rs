@@ -0,0 +1,260 @@
+use std::error::Error;
+use std::fmt;
+
+/// Error returned by [`Arena::replace_if_version`](crate::Arena::replace_if_version).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum VersionError {
+    /// The index was not contained in the arena.
+    Missing,
+
+    /// The entry's version did not match the expected version.
+    Mismatch {
+        /// The version the entry currently has.
+        current: u64,
+    },
+}
+
+impl fmt::Display for VersionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            VersionError::Missing => write!(f, "index is not contained in the arena"),
+            VersionError::Mismatch { current } => {
+                write!(f, "entry version did not match, current is {}", current)
+            }
+        }
+    }
+}
+
+impl Error for VersionError {}
+
+/// Error returned by [`Arena::get2_mut_detailed`](crate::Arena::get2_mut_detailed).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Get2Error {
+    /// Both indices refer to the same entry.
+    Alias,
+
+    /// The first index was not contained in the arena.
+    FirstMissing,
+
+    /// The second index was not contained in the arena.
+    SecondMissing,
+
+    /// Neither index was contained in the arena.
+    BothMissing,
+}
+
+impl fmt::Display for Get2Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Get2Error::Alias => write!(f, "both indices refer to the same entry"),
+            Get2Error::FirstMissing => write!(f, "first index is not contained in the arena"),
+            Get2Error::SecondMissing => write!(f, "second index is not contained in the arena"),
+            Get2Error::BothMissing => write!(f, "neither index is contained in the arena"),
+        }
+    }
+}
+
+impl Error for Get2Error {}
+
+/// Error returned by [`Arena::try_invalidate`](crate::Arena::try_invalidate).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InvalidateError {
+    /// The index was not contained in the arena.
+    Missing,
+
+    /// The entry's generation can't be advanced without wrapping around.
+    GenerationExhausted,
+}
+
+impl fmt::Display for InvalidateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InvalidateError::Missing => write!(f, "index is not contained in the arena"),
+            InvalidateError::GenerationExhausted => {
+                write!(f, "entry generation cannot be advanced any further")
+            }
+        }
+    }
+}
+
+impl Error for InvalidateError {}
+
+/// Error returned by [`Arena::import_state`](crate::Arena::import_state).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ImportStateError {
+    /// The state's slot lists have different lengths, or its length doesn't
+    /// match the arena's.
+    Inconsistent,
+
+    /// A generation in the state was zero, which no arena can produce.
+    InvalidGeneration {
+        /// The slot with the invalid generation.
+        slot: u32,
+    },
+
+    /// A slot's occupancy in the state doesn't match the arena's.
+    OccupancyMismatch {
+        /// The first slot whose occupancy differs.
+        slot: u32,
+    },
+
+    /// The state's free list doesn't contain every empty slot exactly once.
+    InvalidFreeList,
+}
+
+impl fmt::Display for ImportStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ImportStateError::Inconsistent => write!(f, "arena state is inconsistent"),
+            ImportStateError::InvalidGeneration { slot } => {
+                write!(f, "slot {} has an invalid generation", slot)
+            }
+            ImportStateError::OccupancyMismatch { slot } => {
+                write!(
+                    f,
+                    "slot {} is occupied in only one of the arena and the state",
+                    slot
+                )
+            }
+            ImportStateError::InvalidFreeList => {
+                write!(
+                    f,
+                    "free list does not contain every empty slot exactly once"
+                )
+            }
+        }
+    }
+}
+
+impl Error for ImportStateError {}
+
+/// Error returned by [`ArenaLoader::finish`](crate::ArenaLoader::finish).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoadError {
+    /// More than one entry was pushed for the same slot.
+    DuplicateSlot {
+        /// The slot that was pushed more than once.
+        slot: u32,
+    },
+
+    /// An entry was pushed with a generation of zero, which no arena can
+    /// produce.
+    InvalidGeneration {
+        /// The slot of the entry with the invalid generation.
+        slot: u32,
+    },
+
+    /// An entry was pushed for a slot past the limit given to
+    /// [`ArenaLoader::with_max_slots`](crate::ArenaLoader::with_max_slots).
+    SlotOutOfRange {
+        /// The slot of the rejected entry.
+        slot: u32,
+        /// The loader's limit on the number of slots.
+        max_slots: u32,
+    },
+}
+
+impl fmt::Display for LoadError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LoadError::DuplicateSlot { slot } => {
+                write!(f, "slot {} was loaded more than once", slot)
+            }
+            LoadError::InvalidGeneration { slot } => {
+                write!(f, "slot {} was loaded with an invalid generation", slot)
+            }
+            LoadError::SlotOutOfRange { slot, max_slots } => write!(
+                f,
+                "slot {} was loaded, but the arena is limited to {} slots",
+                slot, max_slots
+            ),
+        }
+    }
+}
+
+impl Error for LoadError {}
+
+/// Error describing an inconsistency in an arena's bookkeeping, returned by
+/// [`Arena::validate`](crate::Arena::validate) and
+/// [`Arena::iter_checked`](crate::Arena::iter_checked).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CorruptionError {
+    /// A free list link points past the end of the arena's storage. `slot` is
+    /// the empty slot holding the link, or `None` for the head of the list.
+    FreePointerOutOfRange {
+        /// The slot holding the bad link.
+        slot: Option<u32>,
+    },
+
+    /// A free list link points to a slot that isn't empty. `slot` is the
+    /// empty slot holding the link, or `None` for the head of the list.
+    FreePointerToLiveEntry {
+        /// The slot holding the bad link.
+        slot: Option<u32>,
+    },
+
+    /// The free list loops back on itself or doesn't reach every empty slot.
+    FreeListMismatch,
+
+    /// The arena's length doesn't match the number of values it holds.
+    LengthMismatch {
+        /// The length the arena reports.
+        len: u32,
+        /// The number of values actually in the arena.
+        occupied: u32,
+    },
+}
+
+impl fmt::Display for CorruptionError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CorruptionError::FreePointerOutOfRange { slot: Some(slot) } => {
+                write!(f, "free list link in slot {} is out of range", slot)
+            }
+            CorruptionError::FreePointerOutOfRange { slot: None } => {
+                write!(f, "head of the free list is out of range")
+            }
+            CorruptionError::FreePointerToLiveEntry { slot: Some(slot) } => {
+                write!(f, "free list link in slot {} points to a live entry", slot)
+            }
+            CorruptionError::FreePointerToLiveEntry { slot: None } => {
+                write!(f, "head of the free list points to a live entry")
+            }
+            CorruptionError::FreeListMismatch => {
+                write!(
+                    f,
+                    "free list does not contain every empty slot exactly once"
+                )
+            }
+            CorruptionError::LengthMismatch { len, occupied } => write!(
+                f,
+                "arena reports a length of {} but holds {} values",
+                len, occupied
+            ),
+        }
+    }
+}
+
+impl Error for CorruptionError {}
+
+/// Error returned by [`Arena::merge_preserving`](crate::Arena::merge_preserving).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeError {
+    /// Both arenas have a value or placeholder in the same slot.
+    SlotCollision {
+        /// The first slot that is live in both arenas.
+        slot: u32,
+    },
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MergeError::SlotCollision { slot } => {
+                write!(f, "slot {} is live in both arenas", slot)
+            }
+        }
+    }
+}
+
+impl Error for MergeError {}
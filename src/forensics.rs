@@ -0,0 +1,17 @@
+/// Diagnostic information about the slot an [`Index`](crate::Index) points
+/// to. See [`Arena::forensics`](crate::Arena::forensics).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SlotForensics<'a, T> {
+    /// The generation the slot is currently at, or `None` if the slot is past
+    /// the end of the arena's storage. For empty slots, this is the generation
+    /// of the slot's last occupant.
+    pub current_generation: Option<u32>,
+
+    /// How many generations the slot has advanced since the index was issued,
+    /// or `None` if the slot is past the end of the arena's storage.
+    pub generation_gap: Option<u32>,
+
+    /// The value currently occupying the slot, if any. This may not be the
+    /// value the index originally referred to.
+    pub occupant: Option<&'a T>,
+}
@@ -21,7 +21,7 @@ impl FreePointer {
     }
 
     #[must_use]
-    #[allow(clippy::integer_arithmetic)]
+    #[allow(clippy::arithmetic_side_effects)]
     pub(crate) fn slot(self) -> u32 {
         // This will never underflow due to the field being guaranteed non-zero.
         self.0.get() - 1
@@ -41,6 +41,6 @@ mod test {
     #[test]
     #[should_panic(expected = "u32 overflowed calculating free pointer from u32")]
     fn panic_on_overflow() {
-        let _ = FreePointer::from_slot(std::u32::MAX);
+        let _ = FreePointer::from_slot(u32::MAX);
     }
 }
@@ -0,0 +1,174 @@
+use std::mem::size_of;
+use std::ops;
+
+use crate::arena::{Arena, EmptyEntry, Entry, Index, OccupiedEntry};
+use crate::generation::Generation;
+
+/// Read-only, compact form of an [`Arena`], created with [`Arena::freeze`].
+///
+/// Every index that was valid for the arena stays valid for the frozen arena,
+/// and again once it's turned back into an arena with [`FrozenArena::thaw`].
+/// Values are packed together without per-entry bookkeeping, and the free list
+/// is dropped.
+#[derive(Debug, Clone)]
+pub struct FrozenArena<T> {
+    pub(crate) slots: Box<[FrozenSlot]>,
+    pub(crate) values: Box<[T]>,
+    pub(crate) shell: Arena<T>,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub(crate) enum FrozenSlot {
+    Occupied {
+        generation: Generation,
+        position: u32,
+    },
+    Placeholder(Generation),
+    Empty(Generation),
+}
+
+impl<T> FrozenArena<T> {
+    /// Return the number of elements contained in the frozen arena.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether the frozen arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns true if the given index is valid for the frozen arena.
+    pub fn contains(&self, index: Index) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Get an immutable reference to a value inside the frozen arena by
+    /// [`Index`], returning `None` if the index is not contained in it.
+    pub fn get(&self, index: Index) -> Option<&T> {
+        self.shell.check_provenance(index);
+
+        match self.slots.get(index.slot() as usize) {
+            Some(&FrozenSlot::Occupied {
+                generation,
+                position,
+            }) if generation == index.generation => self.values.get(position as usize),
+            _ => None,
+        }
+    }
+
+    /// Iterate over all of the indexes and values contained in the frozen
+    /// arena, in slot order.
+    pub fn iter(&self) -> impl Iterator<Item = (Index, &T)> {
+        (0u32..)
+            .zip(self.slots.iter())
+            .filter_map(move |(slot, frozen)| match *frozen {
+                FrozenSlot::Occupied {
+                    generation,
+                    position,
+                } => Some((Index { slot, generation }, &self.values[position as usize])),
+                FrozenSlot::Placeholder(_) | FrozenSlot::Empty(_) => None,
+            })
+    }
+
+    /// Return the number of bytes of heap memory used to store the frozen
+    /// arena's entries. See [`Arena::memory_footprint`].
+    pub fn memory_footprint(&self) -> usize {
+        let slots = self
+            .slots
+            .len()
+            .checked_mul(size_of::<FrozenSlot>())
+            .unwrap_or_else(|| unreachable!());
+        let values = self
+            .values
+            .len()
+            .checked_mul(size_of::<T>())
+            .unwrap_or_else(|| unreachable!());
+
+        slots
+            .checked_add(values)
+            .and_then(|total| total.checked_add(self.shell.side_table_footprint()))
+            .unwrap_or_else(|| unreachable!())
+    }
+
+    /// Turn the frozen arena back into an [`Arena`]. Every index that was
+    /// valid before freezing is valid again.
+    ///
+    /// Since the free list was dropped while frozen, empty slots are reused in
+    /// ascending order, like after [`Arena::rebuild_free_list_ascending`].
+    pub fn thaw(self) -> Arena<T> {
+        let mut values = self.values.into_vec().into_iter();
+        let mut storage = Vec::with_capacity(self.slots.len());
+
+        for frozen in self.slots.iter() {
+            storage.push(match *frozen {
+                FrozenSlot::Occupied { generation, .. } => {
+                    let value = values
+                        .next()
+                        .unwrap_or_else(|| unreachable!("frozen arena ran out of values"));
+
+                    Entry::Occupied(OccupiedEntry { generation, value })
+                }
+                FrozenSlot::Placeholder(generation) => Entry::Placeholder(generation),
+                FrozenSlot::Empty(generation) => Entry::Empty(EmptyEntry {
+                    generation,
+                    next_free: None,
+                }),
+            });
+        }
+
+        self.shell.with_storage(storage)
+    }
+}
+
+impl<T> ops::Index<Index> for FrozenArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::Arena;
+
+    #[test]
+    fn freeze_thaw() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..100u64).map(|i| arena.insert(i)).collect();
+        for &index in indices.iter().step_by(3) {
+            arena.remove(index);
+        }
+        let tagged = arena.insert_tagged(1000, 7);
+        let footprint = arena.memory_footprint();
+
+        let frozen = arena.freeze();
+        assert!(frozen.memory_footprint() < footprint);
+        assert_eq!(frozen.len(), 67);
+
+        for (i, &index) in indices.iter().enumerate() {
+            let expected = if i % 3 == 0 { None } else { Some(&(i as u64)) };
+            assert_eq!(frozen.get(index), expected);
+        }
+        assert_eq!(frozen[tagged], 1000);
+        assert_eq!(frozen.iter().count(), 67);
+
+        let mut arena = frozen.thaw();
+        assert_eq!(arena.len(), 67);
+        assert_eq!(arena.validate(), Ok(()));
+        for (i, &index) in indices.iter().enumerate() {
+            assert_eq!(arena.contains(index), i % 3 != 0);
+        }
+        assert_eq!(
+            arena.iter_tagged(7).collect::<Vec<_>>(),
+            vec![(tagged, &1000)]
+        );
+
+        // Stale indices stay stale once slots are reused.
+        let reused = arena.insert(5000);
+        assert_eq!(arena.get(reused), Some(&5000));
+        assert!(indices.iter().all(|&index| arena.get(index) != Some(&5000)));
+    }
+}
@@ -26,6 +26,16 @@ impl Generation {
         Generation(unsafe { NonZeroU32::new_unchecked(next_generation) })
     }
 
+    /// Returns the next generation, or `None` if advancing would wrap around.
+    #[must_use]
+    pub(crate) fn checked_next(self) -> Option<Self> {
+        self.0.get().checked_add(1).map(|next| {
+            // This is safe because a value that didn't overflow after adding 1
+            // can't be zero.
+            Generation(unsafe { NonZeroU32::new_unchecked(next) })
+        })
+    }
+
     pub(crate) fn to_u32(self) -> u32 {
         self.0.get()
     }
@@ -33,6 +43,17 @@ impl Generation {
     pub(crate) fn from_u32(gen: u32) -> Self {
         Generation(NonZeroU32::new(gen).expect("generation IDs must be nonzero!"))
     }
+
+    pub(crate) fn try_from_u32(gen: u32) -> Option<Self> {
+        NonZeroU32::new(gen).map(Generation)
+    }
+
+    /// # Safety
+    ///
+    /// `gen` must not be zero.
+    pub(crate) unsafe fn from_u32_unchecked(gen: u32) -> Self {
+        Generation(NonZeroU32::new_unchecked(gen))
+    }
 }
 
 #[cfg(test)]
@@ -52,10 +73,21 @@ mod test {
 
     #[test]
     fn wrap_on_overflow() {
-        let max = Generation(NonZeroU32::new(std::u32::MAX).unwrap());
-        assert_eq!(max.0.get(), std::u32::MAX);
+        let max = Generation(NonZeroU32::new(u32::MAX).unwrap());
+        assert_eq!(max.0.get(), u32::MAX);
 
         let next = max.next();
         assert_eq!(next.0.get(), 1);
     }
+
+    #[test]
+    fn checked_next() {
+        assert_eq!(
+            Generation::first().checked_next(),
+            Some(Generation::from_u32(2))
+        );
+
+        let max = Generation::from_u32(u32::MAX);
+        assert_eq!(max.checked_next(), None);
+    }
 }
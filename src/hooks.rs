@@ -0,0 +1,104 @@
+use std::collections::HashSet;
+use std::fmt;
+
+use crate::generation::Generation;
+
+/// Generation at which slots are considered close to exhausted unless
+/// configured otherwise.
+const DEFAULT_EXHAUSTION_THRESHOLD: u32 = 0xFFFF_0000;
+
+/// Callbacks that an arena invokes as the generations of its slots advance.
+///
+/// Callbacks can't be cloned, so cloning an arena produces an arena without
+/// any hooks installed.
+pub(crate) struct GenerationHooks {
+    warning: Option<GenerationWarning>,
+    exhaustion_threshold: u32,
+    exhausted: Option<Box<dyn FnMut(u32) + Send + Sync>>,
+}
+
+struct GenerationWarning {
+    threshold: u32,
+    callback: Box<dyn FnMut(u32, u32) + Send + Sync>,
+    warned_slots: HashSet<u32>,
+}
+
+impl GenerationHooks {
+    pub(crate) fn set_warning(
+        &mut self,
+        threshold: u32,
+        callback: Box<dyn FnMut(u32, u32) + Send + Sync>,
+    ) {
+        self.warning = Some(GenerationWarning {
+            threshold,
+            callback,
+            warned_slots: HashSet::new(),
+        });
+    }
+
+    pub(crate) fn set_exhausted(&mut self, callback: Box<dyn FnMut(u32) + Send + Sync>) {
+        self.exhausted = Some(callback);
+    }
+
+    pub(crate) fn set_exhaustion_threshold(&mut self, threshold: u32) {
+        self.exhaustion_threshold = threshold;
+    }
+
+    /// Forget which slots have already been warned about, for when every
+    /// slot's generation starts over.
+    pub(crate) fn forget_slots(&mut self) {
+        if let Some(warning) = &mut self.warning {
+            warning.warned_slots.clear();
+        }
+    }
+
+    /// Called whenever an entry at `slot` with the given generation is removed
+    /// or invalidated. The generation is always the one the entry had before,
+    /// even when invalidating gives it a new one.
+    pub(crate) fn observe(&mut self, slot: u32, generation: Generation) {
+        let generation = generation.to_u32();
+
+        if let Some(warning) = &mut self.warning {
+            if generation >= warning.threshold && warning.warned_slots.insert(slot) {
+                (warning.callback)(slot, generation);
+            }
+        }
+
+        if let Some(exhausted) = &mut self.exhausted {
+            if generation >= self.exhaustion_threshold {
+                exhausted(slot);
+            }
+        }
+    }
+}
+
+impl Default for GenerationHooks {
+    fn default() -> Self {
+        GenerationHooks {
+            warning: None,
+            exhaustion_threshold: DEFAULT_EXHAUSTION_THRESHOLD,
+            exhausted: None,
+        }
+    }
+}
+
+impl Clone for GenerationHooks {
+    fn clone(&self) -> Self {
+        GenerationHooks {
+            exhaustion_threshold: self.exhaustion_threshold,
+            ..GenerationHooks::default()
+        }
+    }
+}
+
+impl fmt::Debug for GenerationHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("GenerationHooks")
+            .field(
+                "warning_threshold",
+                &self.warning.as_ref().map(|warning| warning.threshold),
+            )
+            .field("exhaustion_threshold", &self.exhaustion_threshold)
+            .finish()
+    }
+}
@@ -20,7 +20,7 @@ impl<'a, T> Iterator for IterMut<'a, T> {
             }
 
             match self.inner.next()? {
-                (_, Entry::Empty(_)) => continue,
+                (_, Entry::Empty(_)) | (_, Entry::Placeholder(_)) => continue,
                 (slot, Entry::Occupied(occupied)) => {
                     self.len = self
                         .len
@@ -61,7 +61,7 @@ assert_eq!(arena.get(foo), None);
 
 ## Minimum Supported Rust Version (MSRV)
 
-Thunderdome supports Rust 1.34.1 and newer. Until Thunderdome reaches 1.0,
+Thunderdome supports Rust 1.51.0 and newer. Until Thunderdome reaches 1.0,
 changes to the MSRV will require major version bumps. After 1.0, MSRV changes
 will only require minor version bumps, but will need significant justification.
 */
@@ -70,18 +70,55 @@ will only require minor version bumps, but will need significant justification.
 // This crate is sensitive to integer overflow and wrapping behavior. As such,
 // we should usually use methods like `checked_add` and `checked_sub` instead
 // of the `Add` or `Sub` operators.
-#![deny(clippy::integer_arithmetic)]
+#![deny(clippy::arithmetic_side_effects)]
 
 mod arena;
+mod batch;
+#[cfg(feature = "roaring")]
+mod bitmap;
 mod drain;
+mod error;
+mod forensics;
 mod free_pointer;
+mod frozen;
 mod generation;
+mod hooks;
 mod into_iter;
 mod iter;
 mod iter_mut;
-
-pub use crate::arena::{Arena, Index};
+mod loader;
+mod mark_set;
+mod raw;
+mod remap;
+mod rest;
+#[cfg(feature = "serde")]
+mod serialization;
+mod side_table;
+mod slot_arena;
+mod soa_arena;
+mod state;
+mod zip;
+
+pub use crate::arena::{Arena, Cull, Index, ResolvedRef};
+pub use crate::batch::BatchOps;
 pub use crate::drain::Drain;
+pub use crate::error::{
+    CorruptionError, Get2Error, ImportStateError, InvalidateError, LoadError, MergeError,
+    VersionError,
+};
+pub use crate::forensics::SlotForensics;
+pub use crate::frozen::FrozenArena;
 pub use crate::into_iter::IntoIter;
 pub use crate::iter::Iter;
 pub use crate::iter_mut::IterMut;
+pub use crate::loader::ArenaLoader;
+pub use crate::mark_set::MarkSet;
+pub use crate::raw::{RawArena, RawSlot};
+pub use crate::remap::{remap_keyed_map, RemapIndices};
+pub use crate::rest::ArenaRest;
+#[cfg(feature = "serde")]
+pub use crate::serialization::DeltaGenerations;
+pub use crate::slot_arena::{SlotArena, SlotIndex};
+pub use crate::soa_arena::{SoaArena, SoaDrain, SoaIntoIter, SoaIter, SoaIterMut};
+pub use crate::state::ArenaState;
+pub use crate::zip::{zip_by_slot, zip_by_slot_mut};
@@ -0,0 +1,185 @@
+use crate::arena::{Arena, EmptyEntry, Entry, OccupiedEntry};
+use crate::error::LoadError;
+use crate::generation::Generation;
+
+/// Builds an [`Arena`] one entry at a time, for loading arenas from a stream
+/// without buffering the whole payload first.
+///
+/// Entries can be pushed in any order. Any slots that no entry was pushed for
+/// become empty slots once the arena is finished.
+///
+/// Entries are buffered as they arrive, and the arena's storage is sized once
+/// by [`ArenaLoader::finish`] to fit the highest slot pushed. Since a stream
+/// can name any slot, loaders for untrusted input should be created with
+/// [`ArenaLoader::with_max_slots`].
+#[derive(Debug)]
+pub struct ArenaLoader<T> {
+    entries: Vec<(u32, Generation, T)>,
+    max_slots: Option<u32>,
+    error: Option<LoadError>,
+}
+
+impl<T> ArenaLoader<T> {
+    /// Construct an empty loader.
+    pub fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            max_slots: None,
+            error: None,
+        }
+    }
+
+    /// Construct an empty loader that rejects entries whose slot is not below
+    /// `max_slots`, so the loaded arena never has more than `max_slots` slots.
+    pub fn with_max_slots(max_slots: u32) -> Self {
+        Self {
+            max_slots: Some(max_slots),
+            ..Self::new()
+        }
+    }
+
+    /// Add an entry to the arena being loaded.
+    ///
+    /// Problems with the entry, like a slot that was already pushed, are
+    /// reported by [`ArenaLoader::finish`].
+    pub fn push_entry(&mut self, slot: u32, generation: u32, value: T) {
+        if self.error.is_some() {
+            return;
+        }
+
+        let generation = match Generation::try_from_u32(generation) {
+            Some(generation) => generation,
+            None => {
+                self.error = Some(LoadError::InvalidGeneration { slot });
+                return;
+            }
+        };
+
+        if let Some(max_slots) = self.max_slots {
+            if slot >= max_slots {
+                self.error = Some(LoadError::SlotOutOfRange { slot, max_slots });
+                return;
+            }
+        }
+
+        self.entries.push((slot, generation, value));
+    }
+
+    /// Finish loading, returning the arena or the first problem found with the
+    /// pushed entries.
+    pub fn finish(mut self) -> Result<Arena<T>, LoadError> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+
+        self.entries.sort_unstable_by_key(|&(slot, _, _)| slot);
+        for pair in self.entries.windows(2) {
+            if pair[0].0 == pair[1].0 {
+                return Err(LoadError::DuplicateSlot { slot: pair[0].0 });
+            }
+        }
+
+        let slots = match self.entries.last() {
+            Some(&(slot, _, _)) => (slot as usize)
+                .checked_add(1)
+                .unwrap_or_else(|| unreachable!()),
+            None => 0,
+        };
+        let mut storage = Vec::with_capacity(slots);
+
+        for (slot, generation, value) in self.entries {
+            while storage.len() < slot as usize {
+                storage.push(Entry::Empty(EmptyEntry {
+                    generation: Generation::first(),
+                    next_free: None,
+                }));
+            }
+
+            storage.push(Entry::Occupied(OccupiedEntry { generation, value }));
+        }
+
+        Ok(Arena::from_storage(storage))
+    }
+}
+
+impl<T> Default for ArenaLoader<T> {
+    fn default() -> Self {
+        ArenaLoader::new()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::ArenaLoader;
+    use crate::error::LoadError;
+    use crate::Arena;
+
+    #[test]
+    fn load_out_of_order() {
+        let mut original = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| original.insert(i)).collect();
+        original.remove(indices[1]);
+        original.remove(indices[4]);
+        original.insert(10);
+
+        let mut loader = ArenaLoader::new();
+        let mut entries: Vec<_> = original.iter().collect();
+        entries.reverse();
+        for (index, &value) in entries {
+            let bits = index.to_bits();
+            loader.push_entry(index.slot(), (bits >> 32) as u32, value);
+        }
+
+        let loaded = loader.finish().unwrap();
+        assert_eq!(loaded.len(), original.len());
+        for (index, value) in original.iter() {
+            assert_eq!(loaded.get(index), Some(value));
+        }
+        assert_eq!(loaded.get(indices[1]), None);
+    }
+
+    #[test]
+    fn load_errors() {
+        let mut loader = ArenaLoader::new();
+        loader.push_entry(3, 1, "foo");
+        loader.push_entry(3, 2, "bar");
+        assert_eq!(
+            loader.finish().unwrap_err(),
+            LoadError::DuplicateSlot { slot: 3 }
+        );
+
+        let mut loader = ArenaLoader::new();
+        loader.push_entry(0, 0, "foo");
+        assert_eq!(
+            loader.finish().unwrap_err(),
+            LoadError::InvalidGeneration { slot: 0 }
+        );
+    }
+
+    #[test]
+    fn load_huge_slot() {
+        // Pushing a huge slot only buffers the entry, without growing storage.
+        let mut loader = ArenaLoader::new();
+        loader.push_entry(u32::MAX - 1, 1, "foo");
+        assert_eq!(loader.entries.len(), 1);
+
+        let mut loader = ArenaLoader::with_max_slots(16);
+        loader.push_entry(15, 1, "foo");
+        loader.push_entry(u32::MAX - 1, 1, "bar");
+        loader.push_entry(3, 1, "baz");
+        assert_eq!(loader.entries.len(), 1);
+        assert_eq!(
+            loader.finish().unwrap_err(),
+            LoadError::SlotOutOfRange {
+                slot: u32::MAX - 1,
+                max_slots: 16
+            }
+        );
+
+        let mut loader = ArenaLoader::with_max_slots(16);
+        loader.push_entry(15, 1, "foo");
+        let loaded = loader.finish().unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded.capacity(), 16);
+    }
+}
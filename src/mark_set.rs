@@ -0,0 +1,113 @@
+use crate::arena::Index;
+
+/// Reusable set of indices marked for removal from an arena, for two-phase
+/// mark-and-sweep passes. See [`Arena::sweep_marked`](crate::Arena::sweep_marked).
+///
+/// Sweeping clears the set but keeps its allocation, so the same set can be
+/// used pass after pass without reallocating.
+#[derive(Debug, Clone, Default)]
+pub struct MarkSet {
+    pub(crate) indices: Vec<Index>,
+}
+
+impl MarkSet {
+    /// Construct an empty mark set.
+    pub fn new() -> Self {
+        Self {
+            indices: Vec::new(),
+        }
+    }
+
+    /// Construct an empty mark set with space to hold `capacity` marks
+    /// without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            indices: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Mark an index to be removed by the next sweep.
+    ///
+    /// Marking the same index more than once still only removes its value
+    /// once, but each mark is kept until the sweep and counted by
+    /// [`MarkSet::mark_count`].
+    pub fn mark(&mut self, index: Index) {
+        self.indices.push(index);
+    }
+
+    /// Return the number of marks in the set. An index that was marked more
+    /// than once is counted once per mark.
+    pub fn mark_count(&self) -> usize {
+        self.indices.len()
+    }
+
+    /// Returns whether the set has no marks.
+    pub fn is_empty(&self) -> bool {
+        self.indices.is_empty()
+    }
+
+    /// Return the number of marks the set can hold without allocating.
+    pub fn capacity(&self) -> usize {
+        self.indices.capacity()
+    }
+
+    /// Remove every mark from the set, keeping its allocation.
+    pub fn clear(&mut self) {
+        self.indices.clear();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::MarkSet;
+    use crate::Arena;
+
+    #[test]
+    fn sweep_marked() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+
+        let mut marks = MarkSet::new();
+        for (index, &value) in arena.iter() {
+            if value % 2 == 1 {
+                marks.mark(index);
+            }
+        }
+        marks.mark(indices[1]);
+        assert_eq!(marks.mark_count(), 4);
+        let capacity = marks.capacity();
+
+        assert_eq!(arena.sweep_marked(&mut marks), 3);
+        assert!(marks.is_empty());
+        assert_eq!(marks.capacity(), capacity);
+
+        assert_eq!(arena.len(), 3);
+        for (i, &index) in indices.iter().enumerate() {
+            let expected = if i % 2 == 1 { None } else { Some(&i) };
+            assert_eq!(arena.get(index), expected);
+        }
+
+        // Marks for indices that are no longer live are skipped.
+        marks.mark(indices[3]);
+        marks.mark(indices[4]);
+        assert_eq!(arena.sweep_marked(&mut marks), 1);
+        assert_eq!(marks.capacity(), capacity);
+        assert_eq!(arena.len(), 2);
+    }
+    #[test]
+    fn mark_twice() {
+        let mut arena = Arena::new();
+        let foo = arena.insert("foo");
+        let bar = arena.insert("bar");
+
+        let mut marks = MarkSet::new();
+        marks.mark(foo);
+        marks.mark(foo);
+        assert_eq!(marks.mark_count(), 2);
+
+        assert_eq!(arena.sweep_marked(&mut marks), 1);
+        assert_eq!(marks.mark_count(), 0);
+        assert_eq!(arena.get(foo), None);
+        assert_eq!(arena.get(bar), Some(&"bar"));
+    }
+}
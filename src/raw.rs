@@ -0,0 +1,117 @@
+/// An arena broken down into plain parts, for handing its storage to code
+/// that understands this layout, like an FFI boundary. See
+/// [`Arena::into_raw_parts`](crate::Arena::into_raw_parts) and
+/// [`Arena::from_raw_parts`](crate::Arena::from_raw_parts).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RawArena<T> {
+    /// Every slot of the arena's storage, in order.
+    pub slots: Vec<RawSlot<T>>,
+
+    /// The number of occupied slots.
+    pub len: u32,
+
+    /// The empty slot that the next insertion will reuse, if any. Each empty
+    /// slot links to the one after it through its `next_free` field.
+    pub first_free: Option<u32>,
+
+    /// The provenance id of the arena the parts came from, if it was created
+    /// with [`Arena::with_provenance_checks`](crate::Arena::with_provenance_checks).
+    /// Every generation carries this id in its top byte.
+    pub provenance: Option<u8>,
+}
+
+/// A single slot of a [`RawArena`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RawSlot<T> {
+    /// A slot holding a value.
+    Occupied {
+        /// The generation of the slot's current value.
+        generation: u32,
+        /// The tag the value was inserted with.
+        tag: u16,
+        /// The value in the slot.
+        value: T,
+    },
+
+    /// A slot reserved by
+    /// [`Arena::reserve_placeholder`](crate::Arena::reserve_placeholder).
+    Placeholder {
+        /// The generation of the placeholder.
+        generation: u32,
+    },
+
+    /// A slot without a value.
+    Empty {
+        /// The generation of the slot's last occupant.
+        generation: u32,
+        /// The next empty slot in the free list, if any.
+        next_free: Option<u32>,
+    },
+}
+
+#[cfg(test)]
+mod test {
+    use super::{RawArena, RawSlot};
+    use crate::error::CorruptionError;
+    use crate::Arena;
+
+    #[test]
+    fn raw_parts_roundtrip() {
+        let mut arena = Arena::new();
+        let indices: Vec<_> = (0..6).map(|i| arena.insert(i)).collect();
+        arena.remove(indices[1]);
+        arena.remove(indices[4]);
+        arena.remove(indices[5]);
+        let tagged = arena.insert_tagged(10, 3);
+        let placeholder = arena.reserve_placeholder();
+        let free_list = arena.free_list_snapshot();
+
+        let raw = arena.into_raw_parts();
+        assert_eq!(raw.len, 4);
+        assert_eq!(raw.slots.len(), 6);
+        assert_eq!(raw.first_free, Some(free_list[0]));
+        assert_eq!(
+            raw.slots[tagged.slot() as usize],
+            RawSlot::Occupied {
+                generation: 2,
+                tag: 3,
+                value: 10,
+            }
+        );
+
+        let mut arena = unsafe { Arena::from_raw_parts(raw.clone()) };
+        assert_eq!(arena.validate(), Ok(()));
+        assert_eq!(arena.len(), 4);
+        assert_eq!(arena.free_list_snapshot(), free_list);
+        assert_eq!(arena.get(tagged), Some(&10));
+        assert_eq!(arena.fulfill(placeholder, 20), Ok(()));
+        for &i in &[0, 2, 3] {
+            assert_eq!(arena.get(indices[i]), Some(&i));
+        }
+
+        assert_eq!(arena.into_raw_parts().slots.len(), raw.slots.len());
+    }
+
+    #[test]
+    fn raw_parts_inconsistent() {
+        let raw = RawArena {
+            slots: vec![RawSlot::Occupied {
+                generation: 1,
+                tag: 0,
+                value: "foo",
+            }],
+            len: 2,
+            first_free: None,
+            provenance: None,
+        };
+
+        let arena = unsafe { Arena::from_raw_parts(raw) };
+        assert_eq!(
+            arena.validate(),
+            Err(CorruptionError::LengthMismatch {
+                len: 2,
+                occupied: 1
+            })
+        );
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::HashMap;
+use std::hash::BuildHasher;
+use std::mem::take;
+
+use crate::arena::Index;
+
+/// Types containing [`Index`] values that can be updated using the old-to-new
+/// mapping returned from compacting an arena, like
+/// [`Arena::gc`](crate::Arena::gc).
+///
+/// Indices missing from the mapping referred to values that are no longer in
+/// the arena, and are dropped. Structures that embed indices in several
+/// places can implement this trait by remapping each of their fields:
+///
+/// ```
+/// # use std::collections::HashMap;
+/// use thunderdome::{Index, RemapIndices};
+///
+/// struct Scene {
+///     selected: Option<Index>,
+///     draw_order: Vec<Index>,
+/// }
+///
+/// impl RemapIndices for Scene {
+///     fn remap(&mut self, mapping: &HashMap<Index, Index>) {
+///         self.selected.remap(mapping);
+///         self.draw_order.remap(mapping);
+///     }
+/// }
+/// ```
+pub trait RemapIndices {
+    /// Replace every index with its new index from `mapping`, dropping any
+    /// index that isn't present.
+    fn remap(&mut self, mapping: &HashMap<Index, Index>);
+}
+
+impl RemapIndices for Option<Index> {
+    fn remap(&mut self, mapping: &HashMap<Index, Index>) {
+        *self = self.and_then(|old| mapping.get(&old).copied());
+    }
+}
+
+impl RemapIndices for Vec<Index> {
+    fn remap(&mut self, mapping: &HashMap<Index, Index>) {
+        self.retain(|old| mapping.contains_key(old));
+
+        for index in self.iter_mut() {
+            *index = mapping[index];
+        }
+    }
+}
+
+impl<V, S: BuildHasher + Default> RemapIndices for HashMap<Index, V, S> {
+    fn remap(&mut self, mapping: &HashMap<Index, Index>) {
+        *self = take(self)
+            .into_iter()
+            .filter_map(|(old, value)| mapping.get(&old).map(|&new| (new, value)))
+            .collect();
+    }
+}
+
+/// Rebuild a map keyed by [`Index`] using the old-to-new mapping returned from
+/// compacting an arena, like [`Arena::gc`](crate::Arena::gc).
+///
+/// Entries whose key isn't present in `remap` referred to values that were no
+/// longer in the arena, and are dropped.
+pub fn remap_keyed_map<V>(
+    map: HashMap<Index, V>,
+    remap: &HashMap<Index, Index>,
+) -> HashMap<Index, V> {
+    map.into_iter()
+        .filter_map(|(old, value)| remap.get(&old).map(|&new| (new, value)))
+        .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{remap_keyed_map, RemapIndices};
+    use crate::Arena;
+
+    use std::collections::HashMap;
+
+    #[test]
+    fn remap_side_table() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        let c = arena.insert("c");
+
+        let mut names = HashMap::new();
+        names.insert(a, "first");
+        names.insert(b, "second");
+        names.insert(c, "third");
+
+        arena.remove(a);
+        let remap = arena.gc();
+        let names = remap_keyed_map(names, &remap);
+
+        assert_eq!(names.len(), 2);
+        assert_eq!(names[&remap[&b]], "second");
+        assert_eq!(names[&remap[&c]], "third");
+
+        for (index, value) in arena.iter() {
+            match *value {
+                "b" => assert_eq!(names[&index], "second"),
+                "c" => assert_eq!(names[&index], "third"),
+                _ => unreachable!(),
+            }
+        }
+    }
+
+    #[test]
+    fn remap_indices_trait() {
+        let mut arena = Arena::new();
+        let a = arena.insert("a");
+        let b = arena.insert("b");
+        let c = arena.insert("c");
+
+        let mut order = vec![c, a, b];
+        let mut names = HashMap::new();
+        names.insert(a, "first");
+        names.insert(c, "third");
+        let mut selected = Some(a);
+        let mut hovered = Some(c);
+
+        arena.remove(a);
+        let mapping = arena.gc();
+
+        order.remap(&mapping);
+        names.remap(&mapping);
+        selected.remap(&mapping);
+        hovered.remap(&mapping);
+
+        assert_eq!(order, vec![mapping[&c], mapping[&b]]);
+        assert_eq!(names.len(), 1);
+        assert_eq!(names[&mapping[&c]], "third");
+        assert_eq!(selected, None);
+        assert_eq!(arena.get(hovered.unwrap()), Some(&"c"));
+    }
+}
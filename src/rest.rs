@@ -0,0 +1,52 @@
+use std::cmp::Ordering;
+
+use crate::arena::{check_index_provenance, Entry, Index};
+
+/// Read-only view over every entry of an arena except two that are mutably
+/// borrowed elsewhere. See [`Arena::get2_mut_and_rest`](crate::Arena::get2_mut_and_rest).
+pub struct ArenaRest<'a, T> {
+    pub(crate) front: &'a [Entry<T>],
+    pub(crate) middle: &'a [Entry<T>],
+    pub(crate) back: &'a [Entry<T>],
+    pub(crate) first: u32,
+    pub(crate) second: u32,
+    pub(crate) provenance: Option<u8>,
+}
+
+impl<'a, T> ArenaRest<'a, T> {
+    /// Get an immutable reference to a value by [`Index`], returning `None` if
+    /// the index is not contained in the arena or refers to one of the two
+    /// mutably borrowed entries.
+    pub fn get(&self, index: Index) -> Option<&'a T> {
+        check_index_provenance(self.provenance, index);
+        let slot = index.slot();
+
+        // Offsets are only computed for slots strictly past a hole, so they
+        // can't underflow.
+        let entry = match (slot.cmp(&self.first), slot.cmp(&self.second)) {
+            (Ordering::Less, _) => self.front.get(slot as usize),
+            (Ordering::Greater, Ordering::Less) => slot
+                .checked_sub(self.first)
+                .and_then(|offset| offset.checked_sub(1))
+                .and_then(|offset| self.middle.get(offset as usize)),
+            (_, Ordering::Greater) => slot
+                .checked_sub(self.second)
+                .and_then(|offset| offset.checked_sub(1))
+                .and_then(|offset| self.back.get(offset as usize)),
+            _ => None,
+        };
+
+        match entry {
+            Some(Entry::Occupied(occupied)) if occupied.generation == index.generation => {
+                Some(&occupied.value)
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns true if the given index is contained in the arena and isn't one
+    /// of the two mutably borrowed entries.
+    pub fn contains(&self, index: Index) -> bool {
+        self.get(index).is_some()
+    }
+}
@@ -0,0 +1,128 @@
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// Compact encoding of a list of generations, stored as zigzag-encoded
+/// differences from their median.
+///
+/// After an arena has seen uniform churn, most of its slots have similar
+/// generations, so the differences are much smaller numbers than the
+/// generations themselves. Formats with variable-length integers, or text
+/// formats, store them in fewer bytes. Differences wrap around like `u32`
+/// arithmetic, so each one is exactly as wide as a generation, and
+/// fixed-width formats are no larger than with plain generations.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct DeltaGenerations {
+    /// The median generation that every delta is relative to.
+    pub base: u32,
+
+    /// The zigzag-encoded, wrapping difference of each generation from
+    /// `base`.
+    pub deltas: Vec<u32>,
+}
+
+impl DeltaGenerations {
+    /// Encode a list of generations.
+    pub fn encode(generations: &[u32]) -> Self {
+        let mut sorted = generations.to_vec();
+        sorted.sort_unstable();
+        let base = sorted.get(sorted.len() / 2).copied().unwrap_or(0);
+
+        let deltas = generations
+            .iter()
+            .map(|&generation| zigzag(generation.wrapping_sub(base) as i32))
+            .collect();
+
+        Self { base, deltas }
+    }
+
+    /// Decode back into the list of generations that was encoded.
+    pub fn decode(&self) -> Vec<u32> {
+        self.deltas
+            .iter()
+            .map(|&delta| self.base.wrapping_add(unzigzag(delta) as u32))
+            .collect()
+    }
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn zigzag(value: i32) -> u32 {
+    // Shifts by less than the bit width can't overflow.
+    ((value << 1) ^ (value >> 31)) as u32
+}
+
+#[allow(clippy::arithmetic_side_effects)]
+fn unzigzag(value: u32) -> i32 {
+    ((value >> 1) as i32) ^ ((value & 1) as i32).wrapping_neg()
+}
+
+/// Serializes the generations of an [`ArenaState`](crate::ArenaState) as
+/// [`DeltaGenerations`].
+pub(crate) mod delta_generations {
+    use super::*;
+
+    pub(crate) fn serialize<S: Serializer>(
+        generations: &[u32],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        DeltaGenerations::encode(generations).serialize(serializer)
+    }
+
+    pub(crate) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<u32>, D::Error> {
+        Ok(DeltaGenerations::deserialize(deserializer)?.decode())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::DeltaGenerations;
+    use crate::{Arena, ArenaState};
+
+    #[test]
+    fn delta_generations_roundtrip() {
+        let generations = vec![1, u32::MAX, 500, 499, 501, 0x8000_0000];
+        let encoded = DeltaGenerations::encode(&generations);
+        assert_eq!(encoded.base, 501);
+        assert_eq!(&encoded.deltas[2..5], &[1, 3, 0]);
+        assert_eq!(encoded.decode(), generations);
+
+        let empty = DeltaGenerations::encode(&[]);
+        assert!(empty.decode().is_empty());
+    }
+
+    #[test]
+    fn arena_state_is_smaller() {
+        let mut arena = Arena::new();
+        let mut indices: Vec<_> = (0..64).map(|i| arena.insert(i)).collect();
+
+        // Churn every slot a similar number of times.
+        for round in 0..5000 {
+            let slot = round % indices.len();
+            arena.remove(indices[slot]);
+            indices[slot] = arena.insert(slot);
+        }
+
+        // The same state, with generations serialized as they are.
+        #[derive(serde::Serialize)]
+        struct PlainState<'a> {
+            generations: &'a [u32],
+            occupied: &'a [bool],
+            free_list: &'a [u32],
+            len: u32,
+        }
+
+        let state = arena.export_state();
+        let encoded = serde_json::to_string(&state).unwrap();
+        let plain = serde_json::to_string(&PlainState {
+            generations: &state.generations,
+            occupied: &state.occupied,
+            free_list: &state.free_list,
+            len: state.len,
+        })
+        .unwrap();
+        assert!(encoded.len() < plain.len());
+
+        let decoded: ArenaState = serde_json::from_str(&encoded).unwrap();
+        assert_eq!(decoded, state);
+    }
+}
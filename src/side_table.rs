@@ -0,0 +1,88 @@
+use std::mem::size_of;
+
+/// Per-slot bookkeeping kept beside an arena's entries instead of inside them,
+/// for features that most arenas never use. Slots without a value read as
+/// `V::default()`, and nothing is allocated until some slot is given a value
+/// other than the default.
+///
+/// Most values are only meaningful for occupied slots, so arenas reset a
+/// slot's value whenever a new value is inserted into it.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct SideTable<V> {
+    values: Vec<V>,
+}
+
+impl<V: Copy + Default + PartialEq> SideTable<V> {
+    pub(crate) fn get(&self, slot: u32) -> V {
+        self.values.get(slot as usize).copied().unwrap_or_default()
+    }
+
+    pub(crate) fn set(&mut self, slot: u32, value: V) {
+        let slot = slot as usize;
+
+        if slot >= self.values.len() {
+            if value == V::default() {
+                return;
+            }
+
+            let len = slot.checked_add(1).unwrap_or_else(|| unreachable!());
+            self.values.resize(len, V::default());
+        }
+
+        self.values[slot] = value;
+    }
+
+    /// Move the value at `from` to `to`, resetting `from` to the default.
+    pub(crate) fn move_slot(&mut self, from: u32, to: u32) {
+        let value = self.get(from);
+        self.set(from, V::default());
+        self.set(to, value);
+    }
+
+    /// Forget the values of every slot at or past `len`.
+    pub(crate) fn truncate(&mut self, len: usize) {
+        self.values.truncate(len);
+    }
+
+    pub(crate) fn shrink_to_fit(&mut self) {
+        self.values.shrink_to_fit();
+    }
+
+    pub(crate) fn clear(&mut self) {
+        self.values.clear();
+    }
+
+    /// Return the number of bytes of heap memory used by the table.
+    pub(crate) fn memory_footprint(&self) -> usize {
+        self.values
+            .capacity()
+            .checked_mul(size_of::<V>())
+            .unwrap_or_else(|| unreachable!())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::SideTable;
+
+    #[test]
+    fn allocates_lazily() {
+        let mut table = SideTable::<u64>::default();
+        table.set(10, 0);
+        assert_eq!(table.get(10), 0);
+        assert_eq!(table.memory_footprint(), 0);
+
+        table.set(3, 7);
+        assert_eq!(table.get(3), 7);
+        assert_eq!(table.get(2), 0);
+        assert_eq!(table.get(10), 0);
+        assert!(table.memory_footprint() > 0);
+
+        table.move_slot(3, 5);
+        assert_eq!(table.get(3), 0);
+        assert_eq!(table.get(5), 7);
+
+        table.truncate(5);
+        assert_eq!(table.get(5), 0);
+    }
+}
@@ -0,0 +1,233 @@
+use std::convert::TryInto;
+use std::mem::replace;
+use std::ops;
+
+use crate::free_pointer::FreePointer;
+
+/// Container like [`Arena`](crate::Arena) that skips generation checks
+/// entirely.
+///
+/// Indices are plain slots, so they're half the size of [`Index`](crate::Index)
+/// and lookups only need a bounds check and an occupancy check. In exchange,
+/// an index kept around after its value is removed will silently alias
+/// whatever value is inserted into that slot next. Only use this type when
+/// stale indices can never be observed.
+#[derive(Debug, Clone)]
+pub struct SlotArena<T> {
+    storage: Vec<SlotEntry<T>>,
+    len: u32,
+    first_free: Option<FreePointer>,
+}
+
+/// Index type for [`SlotArena`], containing only a slot.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct SlotIndex {
+    slot: u32,
+}
+
+impl SlotIndex {
+    /// Returns the slot that this index refers to.
+    pub fn slot(self) -> u32 {
+        self.slot
+    }
+}
+
+#[derive(Debug, Clone)]
+enum SlotEntry<T> {
+    Occupied(T),
+    Empty(Option<FreePointer>),
+}
+
+impl<T> SlotArena<T> {
+    /// Construct an empty arena.
+    pub fn new() -> Self {
+        Self {
+            storage: Vec::new(),
+            len: 0,
+            first_free: None,
+        }
+    }
+
+    /// Construct an empty arena with space to hold exactly `capacity` elements
+    /// without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            storage: Vec::with_capacity(capacity),
+            len: 0,
+            first_free: None,
+        }
+    }
+
+    /// Return the number of elements contained in the arena.
+    pub fn len(&self) -> usize {
+        self.len as usize
+    }
+
+    /// Return the number of elements the arena can hold without allocating,
+    /// including the elements currently in the arena.
+    pub fn capacity(&self) -> usize {
+        self.storage.capacity()
+    }
+
+    /// Returns whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    /// Insert a new value into the arena, returning an index that can be used
+    /// to later retrieve the value.
+    pub fn insert(&mut self, value: T) -> SlotIndex {
+        self.len = self
+            .len
+            .checked_add(1)
+            .unwrap_or_else(|| panic!("Cannot insert more than u32::MAX elements into SlotArena"));
+
+        if let Some(free_pointer) = self.first_free {
+            let slot = free_pointer.slot();
+            let entry = self.storage.get_mut(slot as usize).unwrap_or_else(|| {
+                unreachable!("first_free pointed past the end of the arena's storage")
+            });
+
+            self.first_free = match entry {
+                SlotEntry::Empty(next_free) => *next_free,
+                SlotEntry::Occupied(_) => unreachable!("first_free pointed to an occupied entry"),
+            };
+            *entry = SlotEntry::Occupied(value);
+
+            SlotIndex { slot }
+        } else {
+            let slot: u32 = self.storage.len().try_into().unwrap_or_else(|_| {
+                unreachable!("SlotArena storage exceeded what can be represented by a u32")
+            });
+
+            self.storage.push(SlotEntry::Occupied(value));
+
+            SlotIndex { slot }
+        }
+    }
+
+    /// Returns true if the given index is occupied in the arena.
+    pub fn contains(&self, index: SlotIndex) -> bool {
+        self.get(index).is_some()
+    }
+
+    /// Get an immutable reference to a value inside the arena by
+    /// [`SlotIndex`], returning `None` if the slot is empty.
+    pub fn get(&self, index: SlotIndex) -> Option<&T> {
+        match self.storage.get(index.slot as usize) {
+            Some(SlotEntry::Occupied(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Get a mutable reference to a value inside the arena by [`SlotIndex`],
+    /// returning `None` if the slot is empty.
+    pub fn get_mut(&mut self, index: SlotIndex) -> Option<&mut T> {
+        match self.storage.get_mut(index.slot as usize) {
+            Some(SlotEntry::Occupied(value)) => Some(value),
+            _ => None,
+        }
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it if it was present.
+    pub fn remove(&mut self, index: SlotIndex) -> Option<T> {
+        let entry = self.storage.get_mut(index.slot as usize)?;
+
+        match entry {
+            SlotEntry::Occupied(_) => {
+                let old_entry = replace(entry, SlotEntry::Empty(self.first_free));
+                self.first_free = Some(FreePointer::from_slot(index.slot));
+                self.len = self.len.checked_sub(1).unwrap_or_else(|| unreachable!());
+
+                match old_entry {
+                    SlotEntry::Occupied(value) => Some(value),
+                    SlotEntry::Empty(_) => unreachable!(),
+                }
+            }
+            SlotEntry::Empty(_) => None,
+        }
+    }
+
+    /// Clear the arena and drop all elements.
+    pub fn clear(&mut self) {
+        self.storage.clear();
+        self.len = 0;
+        self.first_free = None;
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter(&self) -> impl Iterator<Item = (SlotIndex, &T)> {
+        self.storage
+            .iter()
+            .enumerate()
+            .filter_map(|(slot, entry)| match entry {
+                SlotEntry::Occupied(value) => Some((SlotIndex { slot: slot as u32 }, value)),
+                SlotEntry::Empty(_) => None,
+            })
+    }
+}
+
+impl<T> Default for SlotArena<T> {
+    fn default() -> Self {
+        SlotArena::new()
+    }
+}
+
+impl<T> ops::Index<SlotIndex> for SlotArena<T> {
+    type Output = T;
+
+    fn index(&self, index: SlotIndex) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+impl<T> ops::IndexMut<SlotIndex> for SlotArena<T> {
+    fn index_mut(&mut self, index: SlotIndex) -> &mut Self::Output {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SlotArena, SlotIndex};
+    use crate::Index;
+
+    use std::mem::size_of;
+
+    #[test]
+    fn size_of_slot_index() {
+        assert_eq!(size_of::<SlotIndex>(), 4);
+        assert_eq!(size_of::<SlotIndex>() * 2, size_of::<Index>());
+    }
+
+    #[test]
+    fn insert_get_remove() {
+        let mut arena = SlotArena::new();
+
+        let one = arena.insert(1);
+        let two = arena.insert(2);
+        assert_eq!(arena.len(), 2);
+        assert_eq!(arena.get(one), Some(&1));
+        assert_eq!(arena.get(two), Some(&2));
+
+        *arena.get_mut(two).unwrap() = 20;
+        assert_eq!(arena[two], 20);
+
+        assert_eq!(arena.remove(one), Some(1));
+        assert_eq!(arena.remove(one), None);
+        assert_eq!(arena.get(one), None);
+        assert_eq!(arena.len(), 1);
+
+        // Slots are reused without any generation bump, so the old index now
+        // aliases the new value.
+        let three = arena.insert(3);
+        assert_eq!(three, one);
+        assert_eq!(arena.get(one), Some(&3));
+        assert_eq!(arena.iter().count(), 2);
+    }
+}
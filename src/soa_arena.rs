@@ -0,0 +1,771 @@
+use std::convert::TryInto;
+use std::iter::{ExactSizeIterator, FusedIterator, Zip};
+use std::ops;
+use std::slice;
+use std::vec;
+
+use crate::arena::Index;
+use crate::free_pointer::FreePointer;
+use crate::generation::Generation;
+
+/// Container with the same behavior as [`Arena`](crate::Arena), but with its
+/// bookkeeping stored separately from its values.
+///
+/// Generations and free list links live in their own vector, so operations
+/// that only need to validate indices, like [`SoaArena::contains`], never pull
+/// values into cache. Values are packed together without gaps, so iterating
+/// over them touches no empty slots. This is most useful when `T` is large.
+///
+/// Removing a value moves the last value into its place, so unlike
+/// [`Arena`](crate::Arena), iteration doesn't visit values in slot order.
+#[derive(Debug, Clone)]
+pub struct SoaArena<T> {
+    slots: Vec<SlotState>,
+    values: Vec<T>,
+    value_slots: Vec<u32>,
+    first_free: Option<FreePointer>,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum SlotState {
+    Occupied {
+        generation: Generation,
+        position: u32,
+    },
+    Empty {
+        generation: Generation,
+        next_free: Option<FreePointer>,
+    },
+}
+
+impl<T> SoaArena<T> {
+    /// Construct an empty arena.
+    pub fn new() -> Self {
+        Self {
+            slots: Vec::new(),
+            values: Vec::new(),
+            value_slots: Vec::new(),
+            first_free: None,
+        }
+    }
+
+    /// Construct an empty arena with space to hold exactly `capacity` elements
+    /// without reallocating.
+    pub fn with_capacity(capacity: usize) -> Self {
+        Self {
+            slots: Vec::with_capacity(capacity),
+            values: Vec::with_capacity(capacity),
+            value_slots: Vec::with_capacity(capacity),
+            first_free: None,
+        }
+    }
+
+    /// Return the number of elements contained in the arena.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Return the number of elements the arena can hold without allocating,
+    /// including the elements currently in the arena.
+    pub fn capacity(&self) -> usize {
+        self.values.capacity()
+    }
+
+    /// Reserve capacity for at least `additional` more elements to be
+    /// inserted without reallocating.
+    pub fn reserve(&mut self, additional: usize) {
+        self.slots.reserve(additional);
+        self.values.reserve(additional);
+        self.value_slots.reserve(additional);
+    }
+
+    /// Returns whether the arena is empty.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Insert a new value into the arena, returning an index that can be used
+    /// to later retrieve the value.
+    pub fn insert(&mut self, value: T) -> Index {
+        let position: u32 = match self.values.len().try_into() {
+            Ok(position) if position < u32::MAX => position,
+            _ => panic!("Cannot insert more than u32::MAX elements into SoaArena"),
+        };
+
+        let index = if let Some(free_pointer) = self.first_free {
+            let slot = free_pointer.slot();
+            let state = self.slots.get_mut(slot as usize).unwrap_or_else(|| {
+                unreachable!("first_free pointed past the end of the arena's storage")
+            });
+
+            let generation = match *state {
+                SlotState::Empty {
+                    generation,
+                    next_free,
+                } => {
+                    self.first_free = next_free;
+                    generation.next()
+                }
+                SlotState::Occupied { .. } => {
+                    unreachable!("first_free pointed to an occupied entry")
+                }
+            };
+            *state = SlotState::Occupied {
+                generation,
+                position,
+            };
+
+            Index { slot, generation }
+        } else {
+            let generation = Generation::first();
+            let slot: u32 = self.slots.len().try_into().unwrap_or_else(|_| {
+                unreachable!("SoaArena storage exceeded what can be represented by a u32")
+            });
+
+            self.slots.push(SlotState::Occupied {
+                generation,
+                position,
+            });
+
+            Index { slot, generation }
+        };
+
+        self.values.push(value);
+        self.value_slots.push(index.slot);
+
+        index
+    }
+
+    /// Returns true if the given index is valid for the arena. This only reads
+    /// the arena's bookkeeping, never its values.
+    pub fn contains(&self, index: Index) -> bool {
+        self.position(index).is_some()
+    }
+
+    /// Checks to see whether a slot is occupied in the arena, and if it is,
+    /// returns `Some` with the true `Index` of that slot (slot plus generation.)
+    /// Otherwise, returns `None`.
+    pub fn contains_slot(&self, slot: u32) -> Option<Index> {
+        match self.slots.get(slot as usize) {
+            Some(&SlotState::Occupied { generation, .. }) => Some(Index { slot, generation }),
+            _ => None,
+        }
+    }
+
+    /// Get an immutable reference to a value inside the arena by
+    /// [`Index`], returning `None` if the index is not contained in the arena.
+    pub fn get(&self, index: Index) -> Option<&T> {
+        let position = self.position(index)?;
+        self.values.get(position)
+    }
+
+    /// Get a mutable reference to a value inside the arena by [`Index`],
+    /// returning `None` if the index is not contained in the arena.
+    pub fn get_mut(&mut self, index: Index) -> Option<&mut T> {
+        let position = self.position(index)?;
+        self.values.get_mut(position)
+    }
+
+    /// Get mutable references of two values inside this arena at once by
+    /// [`Index`], returning `None` if the corresponding `index` is not
+    /// contained in this arena.
+    ///
+    /// # Panics
+    /// Panics if the two indices are equal.
+    pub fn get2_mut(&mut self, index1: Index, index2: Index) -> (Option<&mut T>, Option<&mut T>) {
+        assert!(index1 != index2, "Index {:?} was given twice", index1);
+
+        match (self.position(index1), self.position(index2)) {
+            (Some(position1), Some(position2)) => {
+                // Distinct valid indices have distinct slots, whose values are
+                // stored at distinct positions.
+                let (value1, value2) = if position1 < position2 {
+                    let (front, back) = self.values.split_at_mut(position2);
+                    (&mut front[position1], &mut back[0])
+                } else {
+                    let (front, back) = self.values.split_at_mut(position1);
+                    (&mut back[0], &mut front[position2])
+                };
+
+                (Some(value1), Some(value2))
+            }
+            (Some(position1), None) => (self.values.get_mut(position1), None),
+            (None, Some(position2)) => (None, self.values.get_mut(position2)),
+            (None, None) => (None, None),
+        }
+    }
+
+    /// Remove the value contained at the given index from the arena, returning
+    /// it if it was present.
+    pub fn remove(&mut self, index: Index) -> Option<T> {
+        let position = self.position(index)?;
+        self.release_slot(index.slot);
+
+        Some(self.remove_position(position))
+    }
+
+    /// Invalidate the given index and return a new index to the same value. This
+    /// is roughly equivalent to `remove` followed by `insert`, but much faster.
+    /// If the old index is already invalid, this method returns `None`.
+    pub fn invalidate(&mut self, index: Index) -> Option<Index> {
+        match self.slots.get_mut(index.slot as usize) {
+            Some(SlotState::Occupied { generation, .. }) if *generation == index.generation => {
+                *generation = generation.next();
+
+                Some(Index {
+                    generation: *generation,
+                    ..index
+                })
+            }
+            _ => None,
+        }
+    }
+
+    /// Attempt to look up the given slot in the arena, disregarding any generational
+    /// information, and retrieve an immutable reference to it. Returns `None` if the
+    /// slot is empty.
+    pub fn get_by_slot(&self, slot: u32) -> Option<(Index, &T)> {
+        let index = self.contains_slot(slot)?;
+        self.get(index).map(|value| (index, value))
+    }
+
+    /// Remove an entry in the arena by its slot, disregarding any generational info.
+    /// Returns `None` if the slot was already empty.
+    pub fn remove_by_slot(&mut self, slot: u32) -> Option<(Index, T)> {
+        let index = self.contains_slot(slot)?;
+        self.remove(index).map(|value| (index, value))
+    }
+
+    /// Remove all entries in the arena which don't satisfy the provided
+    /// predicate.
+    ///
+    /// Values are visited in slot order, like [`Arena::retain`](crate::Arena::retain),
+    /// so the freed slots are reused in the same order an `Arena` would reuse
+    /// them.
+    pub fn retain<F: FnMut(Index, &mut T) -> bool>(&mut self, mut f: F) {
+        for slot in 0..self.slots.len() {
+            if let SlotState::Occupied {
+                generation,
+                position,
+            } = self.slots[slot]
+            {
+                let slot = slot as u32;
+                let position = position as usize;
+
+                if !f(Index { slot, generation }, &mut self.values[position]) {
+                    self.release_slot(slot);
+                    drop(self.remove_position(position));
+                }
+            }
+        }
+    }
+
+    /// Clear the arena and drop all elements.
+    pub fn clear(&mut self) {
+        self.drain().for_each(drop);
+    }
+
+    /// Remove every value from the arena, returning an iterator over the
+    /// removed indices and values. Values that aren't consumed are dropped
+    /// along with the iterator.
+    ///
+    /// Values are removed in slot order, like [`Arena::drain`](crate::Arena::drain),
+    /// so the freed slots are reused in the same order an `Arena` would reuse
+    /// them.
+    pub fn drain(&mut self) -> SoaDrain<'_, T> {
+        SoaDrain {
+            arena: self,
+            slot: 0,
+        }
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter(&self) -> SoaIter<'_, T> {
+        SoaIter {
+            slots: &self.slots,
+            inner: self.value_slots.iter().zip(self.values.iter()),
+        }
+    }
+
+    /// Iterate over all of the indexes and values contained in the arena, with
+    /// mutable access to each value.
+    ///
+    /// Iteration order is not defined.
+    pub fn iter_mut(&mut self) -> SoaIterMut<'_, T> {
+        SoaIterMut {
+            slots: &self.slots,
+            inner: self.value_slots.iter().zip(self.values.iter_mut()),
+        }
+    }
+
+    /// Returns the position of the value for `index` in `values`, if the index
+    /// is contained in the arena.
+    fn position(&self, index: Index) -> Option<usize> {
+        match self.slots.get(index.slot as usize) {
+            Some(&SlotState::Occupied {
+                generation,
+                position,
+            }) if generation == index.generation => Some(position as usize),
+            _ => None,
+        }
+    }
+
+    /// Turn an occupied slot into an empty one at the head of the free list.
+    /// Its value is left where it is.
+    fn release_slot(&mut self, slot: u32) {
+        let state = &mut self.slots[slot as usize];
+
+        if let SlotState::Occupied { generation, .. } = *state {
+            *state = SlotState::Empty {
+                generation,
+                next_free: self.first_free,
+            };
+            self.first_free = Some(FreePointer::from_slot(slot));
+        }
+    }
+
+    /// Take the value at `position` out of `values`, moving the last value
+    /// into its place.
+    fn remove_position(&mut self, position: usize) -> T {
+        let value = self.values.swap_remove(position);
+        self.value_slots.swap_remove(position);
+
+        if let Some(&moved) = self.value_slots.get(position) {
+            if let SlotState::Occupied {
+                position: moved_position,
+                ..
+            } = &mut self.slots[moved as usize]
+            {
+                // Positions are below the arena's length, which fits in a u32.
+                *moved_position = position as u32;
+            }
+        }
+
+        value
+    }
+}
+
+/// Returns the index of the occupied slot `slot`.
+fn slot_index(slots: &[SlotState], slot: u32) -> Index {
+    match slots[slot as usize] {
+        SlotState::Occupied { generation, .. } => Index { slot, generation },
+        SlotState::Empty { .. } => unreachable!("value belongs to an empty slot"),
+    }
+}
+
+impl<T> Default for SoaArena<T> {
+    fn default() -> Self {
+        SoaArena::new()
+    }
+}
+
+impl<T> IntoIterator for SoaArena<T> {
+    type Item = (Index, T);
+    type IntoIter = SoaIntoIter<T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        SoaIntoIter {
+            slots: self.slots,
+            inner: self.value_slots.into_iter().zip(self.values),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a SoaArena<T> {
+    type Item = (Index, &'a T);
+    type IntoIter = SoaIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, T> IntoIterator for &'a mut SoaArena<T> {
+    type Item = (Index, &'a mut T);
+    type IntoIter = SoaIterMut<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<T> ops::Index<Index> for SoaArena<T> {
+    type Output = T;
+
+    fn index(&self, index: Index) -> &Self::Output {
+        self.get(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+impl<T> ops::IndexMut<Index> for SoaArena<T> {
+    fn index_mut(&mut self, index: Index) -> &mut Self::Output {
+        self.get_mut(index)
+            .unwrap_or_else(|| panic!("No entry at index {:?}", index))
+    }
+}
+
+/// See [`SoaArena::iter`].
+pub struct SoaIter<'a, T> {
+    slots: &'a [SlotState],
+    inner: Zip<slice::Iter<'a, u32>, slice::Iter<'a, T>>,
+}
+
+impl<'a, T> Iterator for SoaIter<'a, T> {
+    type Item = (Index, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&slot, value) = self.inner.next()?;
+        Some((slot_index(self.slots, slot), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for SoaIter<'a, T> {}
+impl<'a, T> ExactSizeIterator for SoaIter<'a, T> {}
+
+/// See [`SoaArena::iter_mut`].
+pub struct SoaIterMut<'a, T> {
+    slots: &'a [SlotState],
+    inner: Zip<slice::Iter<'a, u32>, slice::IterMut<'a, T>>,
+}
+
+impl<'a, T> Iterator for SoaIterMut<'a, T> {
+    type Item = (Index, &'a mut T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&slot, value) = self.inner.next()?;
+        Some((slot_index(self.slots, slot), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T> FusedIterator for SoaIterMut<'a, T> {}
+impl<'a, T> ExactSizeIterator for SoaIterMut<'a, T> {}
+
+/// Iterator type used when a [`SoaArena`] is turned [`IntoIterator`].
+pub struct SoaIntoIter<T> {
+    slots: Vec<SlotState>,
+    inner: Zip<vec::IntoIter<u32>, vec::IntoIter<T>>,
+}
+
+impl<T> Iterator for SoaIntoIter<T> {
+    type Item = (Index, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (slot, value) = self.inner.next()?;
+        Some((slot_index(&self.slots, slot), value))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<T> FusedIterator for SoaIntoIter<T> {}
+impl<T> ExactSizeIterator for SoaIntoIter<T> {}
+
+/// See [`SoaArena::drain`].
+pub struct SoaDrain<'a, T> {
+    arena: &'a mut SoaArena<T>,
+    slot: u32,
+}
+
+impl<'a, T> Iterator for SoaDrain<'a, T> {
+    type Item = (Index, T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            // Stop as soon as the last value is gone, skipping any trailing
+            // empty slots.
+            if self.arena.is_empty() {
+                return None;
+            }
+
+            let slot = self.slot;
+            self.slot = self
+                .slot
+                .checked_add(1)
+                .unwrap_or_else(|| panic!("Overflowed u32 trying to drain SoaArena"));
+
+            // The slot is fully removed from the arena before its value is
+            // handed out, so dropping the iterator part way through leaves the
+            // arena consistent.
+            if let SlotState::Occupied {
+                generation,
+                position,
+            } = self.arena.slots[slot as usize]
+            {
+                self.arena.release_slot(slot);
+                let value = self.arena.remove_position(position as usize);
+
+                return Some((Index { slot, generation }, value));
+            }
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.arena.len(), Some(self.arena.len()))
+    }
+}
+
+impl<'a, T> FusedIterator for SoaDrain<'a, T> {}
+impl<'a, T> ExactSizeIterator for SoaDrain<'a, T> {}
+
+impl<'a, T> Drop for SoaDrain<'a, T> {
+    // Continue iterating/dropping if there are any elements left.
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::{SlotState, SoaArena};
+    use crate::{Arena, ArenaState, Index};
+
+    /// Assert that both arenas hold the same indices and values.
+    fn assert_same(arena: &Arena<u32>, soa: &SoaArena<u32>) {
+        let mut soa_entries: Vec<_> = soa.iter().collect();
+        soa_entries.sort();
+
+        assert_eq!(arena.iter().collect::<Vec<_>>(), soa_entries);
+        assert_eq!(arena.len(), soa.len());
+        assert_eq!(arena.is_empty(), soa.is_empty());
+    }
+
+    #[test]
+    fn matches_arena() {
+        let mut arena = Arena::new();
+        let mut soa = SoaArena::new();
+
+        let mut arena_indices = Vec::new();
+        let mut soa_indices = Vec::new();
+        for i in 0..10 {
+            arena_indices.push(arena.insert(i));
+            soa_indices.push(soa.insert(i));
+        }
+        assert_eq!(arena_indices, soa_indices);
+
+        for &i in &[3, 7, 1] {
+            assert_eq!(arena.remove(arena_indices[i]), soa.remove(soa_indices[i]));
+            assert_eq!(soa.remove(soa_indices[i]), None);
+        }
+
+        for i in 10..15 {
+            assert_eq!(arena.insert(i), soa.insert(i));
+        }
+
+        arena[arena_indices[0]] = 100;
+        soa[soa_indices[0]] = 100;
+
+        for &index in &arena_indices {
+            assert_eq!(arena.get(index), soa.get(index));
+            assert_eq!(arena.contains(index), soa.contains(index));
+        }
+        assert_same(&arena, &soa);
+
+        soa.clear();
+        assert!(soa.is_empty());
+        assert_eq!(soa.iter().count(), 0);
+        assert!(!soa.contains(soa_indices[0]));
+    }
+
+    #[test]
+    fn remove_and_reuse_matches_arena() {
+        let mut arena = Arena::new();
+        let mut soa = SoaArena::new();
+        let mut live = Vec::new();
+        let mut dead = Vec::new();
+
+        // A fixed pseudo-random sequence of insertions and removals, so that
+        // slots are reused in a tangled order.
+        let mut state = 12345u32;
+        for step in 0..500 {
+            state = state.wrapping_mul(1_103_515_245).wrapping_add(12345);
+
+            let choice = state % 3;
+            if live.is_empty() || choice > 0 {
+                let index = arena.insert(step);
+                assert_eq!(soa.insert(step), index);
+                live.push(index);
+            } else {
+                let index = live.swap_remove(state as usize % live.len());
+                assert_eq!(arena.remove(index), soa.remove(index));
+                dead.push(index);
+            }
+
+            if step % 50 == 0 {
+                assert_same(&arena, &soa);
+            }
+        }
+
+        assert_same(&arena, &soa);
+        for &index in live.iter().chain(dead.iter()) {
+            assert_eq!(arena.contains(index), soa.contains(index));
+            assert_eq!(arena.get(index), soa.get(index));
+            assert_eq!(soa.get_mut(index).is_some(), arena.contains(index));
+        }
+    }
+
+    #[test]
+    fn generation_wrap_matches_arena() {
+        let mut arena = Arena::new();
+        let mut soa = SoaArena::new();
+        let first = arena.insert(1);
+        assert_eq!(soa.insert(1), first);
+        arena.remove(first);
+        soa.remove(first);
+
+        // Push slot 0 to the last generation before it wraps around.
+        arena
+            .import_state(ArenaState {
+                generations: vec![u32::MAX - 1],
+                occupied: vec![false],
+                free_list: vec![0],
+                len: 0,
+            })
+            .unwrap();
+        if let SlotState::Empty { generation, .. } = &mut soa.slots[0] {
+            *generation = crate::generation::Generation::from_u32(u32::MAX - 1);
+        }
+
+        let last = arena.insert(2);
+        assert_eq!(soa.insert(2), last);
+        assert_eq!(last.to_bits() >> 32, u64::from(u32::MAX));
+        arena.remove(last);
+        soa.remove(last);
+
+        let wrapped = arena.insert(3);
+        assert_eq!(soa.insert(3), wrapped);
+        assert_eq!(wrapped, Index::from_bits(1 << 32));
+        assert!(!soa.contains(last));
+        assert_same(&arena, &soa);
+    }
+
+    #[test]
+    fn slot_access_matches_arena() {
+        let mut arena = Arena::new();
+        let mut soa = SoaArena::default();
+        for i in 0..6 {
+            assert_eq!(arena.insert(i), soa.insert(i));
+        }
+        arena.remove_by_slot(2);
+        assert_eq!(
+            soa.remove_by_slot(2),
+            Some((Index::from_bits((1 << 32) | 2), 2))
+        );
+        assert_eq!(soa.remove_by_slot(2), None);
+
+        for slot in 0..8 {
+            assert_eq!(arena.contains_slot(slot), soa.contains_slot(slot));
+            assert_eq!(arena.get_by_slot(slot), soa.get_by_slot(slot));
+        }
+
+        let (index, _) = soa.get_by_slot(4).unwrap();
+        let invalidated = soa.invalidate(index).unwrap();
+        assert_eq!(arena.invalidate(index), Some(invalidated));
+        assert_eq!(soa.invalidate(index), None);
+        assert!(!soa.contains(index));
+        assert_eq!(soa.get(invalidated), Some(&4));
+        assert_eq!(soa.contains_slot(4), Some(invalidated));
+        assert_same(&arena, &soa);
+    }
+
+    #[test]
+    fn get2_mut() {
+        let mut soa = SoaArena::new();
+        let foo = soa.insert(1);
+        let bar = soa.insert(2);
+        let baz = soa.insert(3);
+        soa.remove(foo);
+
+        // `baz` was moved into the position `foo` left behind.
+        let (a, b) = soa.get2_mut(baz, bar);
+        std::mem::swap(a.unwrap(), b.unwrap());
+        assert_eq!(soa[bar], 3);
+        assert_eq!(soa[baz], 2);
+
+        let (a, b) = soa.get2_mut(foo, bar);
+        assert!(a.is_none());
+        *b.unwrap() += 10;
+        assert_eq!(soa[bar], 13);
+
+        assert_eq!(soa.get2_mut(bar, foo).1, None);
+        assert_eq!(
+            soa.get2_mut(foo, Index::from_bits(1 << 32 | 9)),
+            (None, None)
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "was given twice")]
+    fn get2_mut_same_index() {
+        let mut soa = SoaArena::new();
+        let foo = soa.insert(1);
+        soa.get2_mut(foo, foo);
+    }
+
+    #[test]
+    fn reserve() {
+        let mut soa = SoaArena::new();
+        soa.insert(1);
+        soa.reserve(10);
+        assert!(soa.capacity() >= 11);
+
+        let capacity = soa.capacity();
+        for i in 0..10 {
+            soa.insert(i);
+        }
+        assert_eq!(soa.capacity(), capacity);
+    }
+
+    #[test]
+    fn retain_drain_matches_arena() {
+        let mut arena = Arena::new();
+        let mut soa = SoaArena::new();
+        for i in 0..20 {
+            assert_eq!(arena.insert(i), soa.insert(i));
+        }
+
+        arena.retain(|_, value| *value % 3 != 0);
+        soa.retain(|_, value| *value % 3 != 0);
+        assert_same(&arena, &soa);
+
+        for ((_, a), (_, b)) in arena.iter_mut().zip(soa.iter_mut()) {
+            *a += 100;
+            *b += 100;
+        }
+        for (_, value) in &mut soa {
+            assert!(*value >= 100);
+        }
+        assert_eq!((&soa).into_iter().len(), soa.len());
+
+        let drained: Vec<_> = soa.drain().collect();
+        assert_eq!(drained, arena.drain().collect::<Vec<_>>());
+        assert!(soa.is_empty());
+
+        // Slots freed by retaining and draining are reused in the same order
+        // by both.
+        for i in 0..25 {
+            assert_eq!(arena.insert(i), soa.insert(i));
+        }
+        arena.retain(|_, value| *value % 2 == 0);
+        soa.retain(|_, value| *value % 2 == 0);
+        for i in 0..10 {
+            assert_eq!(arena.insert(i), soa.insert(i));
+        }
+
+        let mut owned: Vec<_> = soa.clone().into_iter().collect();
+        owned.sort();
+        assert_eq!(owned, arena.into_iter().collect::<Vec<_>>());
+    }
+}
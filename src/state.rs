@@ -0,0 +1,32 @@
+/// Snapshot of an arena's bookkeeping, without its values. See
+/// [`Arena::export_state`](crate::Arena::export_state).
+///
+/// Two arenas with the same state hand out the same indices for the same
+/// sequence of insertions and removals, which is useful for keeping arenas in
+/// lockstep across peers.
+///
+/// With the `serde` feature, states can be serialized and deserialized.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ArenaState {
+    /// The generation of each slot in the arena's storage. For empty slots,
+    /// this is the generation of the slot's last occupant.
+    ///
+    /// With the `serde` feature, these are serialized as
+    /// `DeltaGenerations`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(with = "crate::serialization::delta_generations")
+    )]
+    pub generations: Vec<u32>,
+
+    /// Whether each slot in the arena's storage is occupied. Placeholders
+    /// count as occupied.
+    pub occupied: Vec<bool>,
+
+    /// The empty slots of the arena, in the order they will be reused.
+    pub free_list: Vec<u32>,
+
+    /// The number of values in the arena.
+    pub len: u32,
+}
@@ -0,0 +1,78 @@
+use crate::arena::{Arena, Entry};
+
+/// Walk two arenas in lockstep by slot, yielding the slot and both values for
+/// every slot that is occupied in both arenas. Generations are not compared.
+///
+/// Useful when several arenas store different components for the same set of
+/// slots.
+pub fn zip_by_slot<'a, A, B>(
+    a: &'a Arena<A>,
+    b: &'a Arena<B>,
+) -> impl Iterator<Item = (u32, &'a A, &'a B)> {
+    (0u32..)
+        .zip(a.entries().iter().zip(b.entries().iter()))
+        .filter_map(|(slot, entries)| match entries {
+            (Entry::Occupied(a), Entry::Occupied(b)) => Some((slot, &a.value, &b.value)),
+            _ => None,
+        })
+}
+
+/// Walk two arenas in lockstep by slot, yielding the slot and mutable
+/// references to both values for every slot that is occupied in both arenas.
+/// Generations are not compared.
+pub fn zip_by_slot_mut<'a, A, B>(
+    a: &'a mut Arena<A>,
+    b: &'a mut Arena<B>,
+) -> impl Iterator<Item = (u32, &'a mut A, &'a mut B)> {
+    (0u32..)
+        .zip(a.entries_mut().iter_mut().zip(b.entries_mut().iter_mut()))
+        .filter_map(|(slot, entries)| match entries {
+            (Entry::Occupied(a), Entry::Occupied(b)) => Some((slot, &mut a.value, &mut b.value)),
+            _ => None,
+        })
+}
+
+#[cfg(test)]
+mod test {
+    use super::{zip_by_slot, zip_by_slot_mut};
+    use crate::Arena;
+
+    #[test]
+    fn zip_partial_overlap() {
+        let mut positions = Arena::new();
+        let mut names = Arena::new();
+
+        for i in 0..5 {
+            positions.insert(i * 10);
+        }
+        for name in &["a", "b", "c"] {
+            names.insert(*name);
+        }
+
+        let removed = positions.contains_slot(1).unwrap();
+        positions.remove(removed);
+
+        let zipped: Vec<_> = zip_by_slot(&positions, &names).collect();
+        assert_eq!(zipped, vec![(0, &0, &"a"), (2, &20, &"c")]);
+    }
+
+    #[test]
+    fn zip_mut_partial_overlap() {
+        let mut a = Arena::new();
+        let mut b = Arena::new();
+
+        let a_indices: Vec<_> = (0..4).map(|i| a.insert(i)).collect();
+        let b_indices: Vec<_> = (0..3).map(|i| b.insert(i * 10)).collect();
+        b.remove(b_indices[1]);
+
+        for (_, a_value, b_value) in zip_by_slot_mut(&mut a, &mut b) {
+            *a_value += 1;
+            *b_value += 1;
+        }
+
+        let a_values: Vec<_> = a_indices.iter().map(|&index| a[index]).collect();
+        assert_eq!(a_values, vec![1, 1, 3, 3]);
+        assert_eq!(b[b_indices[0]], 1);
+        assert_eq!(b[b_indices[2]], 21);
+    }
+}